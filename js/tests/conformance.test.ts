@@ -0,0 +1,78 @@
+import { test, expect, describe } from "@jest/globals";
+import { readFileSync } from "fs";
+import { join } from "path";
+import { PublicKey } from "@solana/web3.js";
+import { CENTRAL_STATE_SNS_RECORDS } from "@bonfida/sns-records";
+import { REVERSE_LOOKUP_CLASS, ROOT_DOMAIN_ACCOUNT } from "../src/constants";
+import { getRecordKeySync } from "../src/record/getRecordKeySync";
+import { getRecordV2Key } from "../src/record_v2/getRecordV2Key";
+import { Record } from "../src/types/record";
+import { getDomainKeySync } from "../src/utils/getDomainKeySync";
+import { getHashedNameSync } from "../src/utils/getHashedNameSync";
+import { getNameAccountKeySync } from "../src/utils/getNameAccountKeySync";
+import { getReverseKeySync } from "../src/utils/getReverseKeySync";
+
+// Test vectors shared by all the SNS SDKs, the Rust SDK checks the same file
+const { derivation } = JSON.parse(
+  readFileSync(join(__dirname, "../../test-vectors/derivation.json"), "utf8"),
+) as {
+  derivation: {
+    domain: string;
+    domain_key: string;
+    reverse_key: string;
+    records: { record: string; v1: string; v2: string }[];
+  }[];
+};
+
+const derive = (name: string, parent: PublicKey, nameClass?: PublicKey) =>
+  getNameAccountKeySync(getHashedNameSync(name), nameClass, parent);
+
+/**
+ * Derives a domain of any depth label by label from the name service seeds,
+ * since `getDomainKeySync` does not support sub-subdomains
+ */
+const deriveChain = (domain: string) => {
+  const labels = domain.replace(/\.sol$/, "").split(".").reverse();
+  let parent = ROOT_DOMAIN_ACCOUNT;
+  let key = derive(labels[0], parent);
+  for (const label of labels.slice(1)) {
+    parent = key;
+    key = derive("\0".concat(label), parent);
+  }
+  return { key, parent };
+};
+
+describe("Derivation vectors", () => {
+  test.each(derivation)("$domain", (v) => {
+    const { key, parent } = deriveChain(v.domain);
+    const isSub = !parent.equals(ROOT_DOMAIN_ACCOUNT);
+    expect(key.toBase58()).toBe(v.domain_key);
+    expect(
+      getNameAccountKeySync(
+        getHashedNameSync(key.toBase58()),
+        REVERSE_LOOKUP_CLASS,
+        isSub ? parent : undefined,
+      ).toBase58(),
+    ).toBe(v.reverse_key);
+    for (const r of v.records) {
+      expect(derive("\x01".concat(r.record), key).toBase58()).toBe(r.v1);
+      expect(
+        derive(
+          "\x02".concat(r.record),
+          key,
+          CENTRAL_STATE_SNS_RECORDS,
+        ).toBase58(),
+      ).toBe(r.v2);
+    }
+
+    // The SDK helpers only support domains and direct subdomains
+    if (v.domain.split(".").length > 3) return;
+    expect(getDomainKeySync(v.domain).pubkey.toBase58()).toBe(v.domain_key);
+    expect(getReverseKeySync(v.domain, isSub).toBase58()).toBe(v.reverse_key);
+    for (const r of v.records) {
+      const record = r.record as Record;
+      expect(getRecordKeySync(v.domain, record).toBase58()).toBe(r.v1);
+      expect(getRecordV2Key(v.domain, record).toBase58()).toBe(r.v2);
+    }
+  });
+});
@@ -1,7 +1,9 @@
 use serde::Serialize;
 use sns_sdk::{
+    error::SnsError,
     favourite_domain::register_favourite::Accounts,
-    record::{self, get_record_v2_key},
+    fetcher::AccountFetcher,
+    record::{self, get_record_v2_key, record_v1},
     NAME_OFFERS_PROGRAM_ID,
 };
 use solana_account_decoder::UiAccountEncoding;
@@ -22,10 +24,7 @@ use {
     prettytable::{row, Table},
     serde::Deserialize,
     sns_sdk::non_blocking::resolve,
-    sns_sdk::{
-        derivation::{get_domain_key, get_hashed_name},
-        record::Record,
-    },
+    sns_sdk::{derivation::get_domain_key, domain_name::DomainName, record::Record},
     solana_client::nonblocking::rpc_client::RpcClient,
     solana_program::instruction::{AccountMeta, Instruction},
     solana_program::program_pack::Pack,
@@ -154,14 +153,30 @@ enum Commands {
         #[arg(long, short, help = "Optional custom RPC URL")]
         url: Option<String>,
     },
-    #[command(arg_required_else_help = true)]
+    #[command(
+        arg_required_else_help = true,
+        about = "Bridge a domain name to another chain"
+    )]
     Bridge {
-        #[arg(required = true)]
+        #[arg(
+            required = true,
+            help = "The chain to bridge the domain to: bsc, injective or base"
+        )]
         target_chain: String,
-        #[arg(required = true)]
+        #[arg(
+            required = true,
+            help = "The domain to bridge with or without .sol suffix"
+        )]
         domain: String,
-        #[arg(required = true)]
+        #[arg(
+            required = true,
+            help = "The path to the wallet private key which currently owns the domain"
+        )]
         keypair_path: String,
+        #[arg(long, help = "Only print the bridge request without sending it")]
+        dry_run: bool,
+        #[arg(long, short, help = "Optional custom RPC URL")]
+        url: Option<String>,
     },
     #[command(
         arg_required_else_help = true,
@@ -205,6 +220,11 @@ pub enum RecordSubCommand {
         content: String,
         #[clap(long, help = "The path of keypair ownning the domain")]
         keypair: String,
+        #[clap(
+            long,
+            help = "Skip the validation of textual content, e.g. URLs and handles"
+        )]
+        no_validate: bool,
     },
     #[command(about = "Dump records system info")]
     SystemDump,
@@ -247,6 +267,10 @@ pub fn progress_bar(len: usize) -> ProgressBar {
 
 type CliResult = Result<(), Box<dyn std::error::Error>>;
 
+/// The chains domains can be bridged to with their Wormhole chain ID, see
+/// `sns_warp_common::wormhole::ChainIds`
+const BRIDGE_TARGET_CHAINS: [(&str, u16); 3] = [("bsc", 4), ("injective", 19), ("base", 30)];
+
 async fn process_domains(rpc_client: &RpcClient, owners: Vec<String>) -> CliResult {
     println!("Resolving domains...\n");
     let mut table = Table::new();
@@ -372,6 +396,57 @@ async fn process_transfer(
     Ok(())
 }
 
+async fn process_bridge(
+    rpc_client: &RpcClient,
+    target_chain: &str,
+    domain: &str,
+    keypair_path: &str,
+    dry_run: bool,
+) -> CliResult {
+    let (chain, chain_id) = BRIDGE_TARGET_CHAINS
+        .iter()
+        .find(|(chain, _)| chain.eq_ignore_ascii_case(target_chain))
+        .ok_or_else(|| {
+            let chains = BRIDGE_TARGET_CHAINS.map(|(chain, _)| chain).join(", ");
+            anyhow!("Unsupported target chain {target_chain}, expected one of: {chains}")
+        })?;
+    let keypair = read_keypair_file(keypair_path)?;
+    let domain_key = get_domain_key(domain)?;
+    let Some((header, _)) = resolve::resolve_name_registry(rpc_client, &domain_key).await? else {
+        return Err(anyhow!("Domain not found: {}", format_domain(domain)).into());
+    };
+    if header.owner != keypair.pubkey() {
+        return Err(anyhow!(
+            "{} is owned by {}, not by {}",
+            format_domain(domain),
+            header.owner,
+            keypair.pubkey()
+        )
+        .into());
+    }
+
+    let mut table = Table::new();
+    table.add_row(row!["Domain", "Domain key", "Owner", "Target chain"]);
+    table.add_row(row![
+        format_domain(domain),
+        domain_key,
+        header.owner,
+        format!("{chain} ({chain_id})")
+    ]);
+    table.printstd();
+    if dry_run {
+        println!("Dry run: no transaction was sent");
+    }
+
+    // The presets do not set the warp program ID, which is not published with sns-warp-common,
+    // and neither are its instructions to build the bridge transactions
+    rpc_client
+        .config()
+        .warp_program_id
+        .ok_or(SnsError::WarpProgramNotSet)?;
+    Err(anyhow!("Bridging domains is not supported by this version of the CLI").into())
+}
+
 async fn process_lookup(rpc_client: &RpcClient, domains: Vec<String>) -> CliResult {
     println!("Fetching information...\n");
     let mut table = Table::new();
@@ -457,17 +532,16 @@ async fn process_register(
     let client = reqwest::Client::new();
     let keypair = read_keypair_file(keypair_path)?;
 
-    let re = regex::Regex::new(r"^[a-z\d\-_]+$").unwrap();
-
     for (idx, domain) in domains.into_iter().enumerate() {
-        if !re.is_match(&domain) {
-            return Err(anyhow!("Invalid domain").into());
+        let domain = DomainName::parse(&domain)?;
+        if domain.is_subdomain() {
+            return Err(anyhow!("Subdomains cannot be registered: {domain}").into());
         }
         let response = client
             .get(format!(
                 "https://sns-sdk-proxy.bonfida.workers.dev/register?buyer={}&domain={}&space={}",
                 keypair.pubkey(),
-                domain,
+                domain.as_str(),
                 space
             ))
             .send()
@@ -567,7 +641,7 @@ async fn process_register_favourite(
             println!("Favourite set, txid: {sig}");
         }
         OwnerKind::Pubkey(_) => {
-            let mut tx = Transaction::new_with_payer(&[ix.clone()], Some(&owner));
+            let mut tx = Transaction::new_with_payer(std::slice::from_ref(&ix), Some(&owner));
             tx.message.recent_blockhash = blockhash;
 
             println!(
@@ -586,98 +660,90 @@ async fn process_record_set(
     record_str: &str,
     content: &str,
     keypair_path: &str,
+    no_validate: bool,
 ) -> CliResult {
-    let mut ixs = vec![];
     let mut table = Table::new();
     table.add_row(row!["Transaction", "Signature"]);
 
     let record = Record::try_from_str(record_str)?;
     let keypair = read_keypair_file(keypair_path)?;
-    let data = sns_sdk::record::record_v1::serialize_record(content, record)?;
-    let key = record::get_record_key(
-        domain,
-        Record::try_from_str(record_str)?,
-        record::RecordVersion::V1,
-    )?;
-    let hashed_name = get_hashed_name(&format!("\x01{record_str}"));
-    let parent = get_domain_key(domain)?;
-
-    let lamports = rpc_client
-        .get_minimum_balance_for_rent_exemption(data.len() + NameRecordHeader::LEN)
+    let owner = keypair.pubkey();
+    let data = if record == Record::Sol {
+        let content = Pubkey::from_str(content)?;
+        let key = record::get_record_v1_key(domain, record)?;
+        record_v1::sign_sol_record(&content, &key, &keypair)?
+    } else if no_validate {
+        record_v1::serialize_record_unchecked(content, record)?
+    } else {
+        record_v1::serialize_record(content, record)?
+    };
+    let ixs = sns_sdk::non_blocking::record::set_record_v1(
+        rpc_client, domain, record, data, &owner, &owner,
+    )
+    .await?;
+
+    let mut tx = Transaction::new_with_payer(&ixs, Some(&owner));
+    let blockhash = rpc_client.get_latest_blockhash().await?;
+    tx.sign(&[&keypair], blockhash);
+
+    let sig = rpc_client
+        .send_and_confirm_transaction_with_spinner_and_commitment(
+            &tx,
+            CommitmentConfig {
+                commitment: CommitmentLevel::Processed,
+            },
+        )
         .await?;
+    table.add_row(row!["Update record", make_tx_url(&sig.to_string())]);
+
+    Term::stdout().clear_to_end_of_screen()?;
+    table.printstd();
+
+    Ok(())
+}
+
+async fn process_record_v2_set(
+    rpc_client: &RpcClient,
+    domain: &str,
+    record_str: &str,
+    content: &str,
+    keypair_path: &str,
+    no_validate: bool,
+) -> CliResult {
+    let mut table = Table::new();
+    table.add_row(row!["Transaction", "Signature"]);
+
+    let record = Record::try_from_str(record_str)?;
+    let keypair = read_keypair_file(keypair_path)?;
+    let owner = keypair.pubkey();
+    let key = get_record_v2_key(domain, record)?;
+    let data = if no_validate {
+        record::record_v2::serialize_record_v2_content_unchecked(content, record)?
+    } else {
+        record::record_v2::serialize_record_v2_content(content, record)?
+    };
 
     let acc = rpc_client
         .get_account_with_commitment(&key, CommitmentConfig::default())
         .await?;
 
-    if let Some(value) = acc.value {
-        if value.data.len() - NameRecordHeader::LEN != data.len() {
-            // Delete existing record
-            // This is the only way to handle the account resizing
-            let ix = spl_name_service::instruction::delete(
-                spl_name_service::ID,
-                key,
-                keypair.pubkey(),
-                keypair.pubkey(),
-            )?;
-
-            // Clean up transaction
-            let mut tx = Transaction::new_with_payer(&[ix], Some(&keypair.pubkey()));
-            let blockhash = rpc_client.get_latest_blockhash().await?;
-            tx.sign(&[&keypair], blockhash);
-
-            let sig = rpc_client
-                .send_and_confirm_transaction_with_spinner(&tx)
-                .await?;
-            table.add_row(row!["Clean up", make_tx_url(&sig.to_string())]);
-
-            // Create the record
-            let ix = spl_name_service::instruction::create(
-                spl_name_service::ID,
-                spl_name_service::instruction::NameRegistryInstruction::Create {
-                    hashed_name,
-                    lamports,
-                    space: data.len() as u32,
-                },
-                key,
-                keypair.pubkey(),
-                keypair.pubkey(),
-                None,
-                Some(parent),
-                Some(keypair.pubkey()),
-            )?;
-            ixs.push(ix);
-        }
+    let mut ixs = vec![if acc.value.is_some() {
+        record::record_v2::update_raw_record_v2_instruction(domain, record, data, &owner, &owner)?
     } else {
-        let ix: Instruction = spl_name_service::instruction::create(
-            spl_name_service::ID,
-            spl_name_service::instruction::NameRegistryInstruction::Create {
-                hashed_name,
-                lamports,
-                space: data.len() as u32,
-            },
-            key,
-            keypair.pubkey(),
-            keypair.pubkey(),
-            None,
-            Some(parent),
-            Some(keypair.pubkey()),
-        )?;
-        ixs.push(ix);
+        record::record_v2::create_raw_record_v2_instruction(domain, record, data, &owner, &owner)?
+    }];
+    // The owner signs for staleness, and for the right of association of a SOL record pointing
+    // to itself
+    ixs.push(record::record_v2::validate_record_v2_content(
+        true, domain, record, &owner, &owner, &owner,
+    )?);
+    if record == Record::Sol && Pubkey::from_str(content)? == owner {
+        ixs.push(record::record_v2::validate_record_v2_content(
+            false, domain, record, &owner, &owner, &owner,
+        )?);
     }
 
-    // Update
-    let ix = spl_name_service::instruction::update(
-        spl_name_service::ID,
-        0,
-        data,
-        key,
-        keypair.pubkey(),
-        Some(parent),
-    )?;
-    ixs.push(ix);
-
-    let mut tx = Transaction::new_with_payer(&ixs, Some(&keypair.pubkey()));
+    let mut tx = Transaction::new_with_payer(&ixs, Some(&owner));
     let blockhash = rpc_client.get_latest_blockhash().await?;
     tx.sign(&[&keypair], blockhash);
 
@@ -703,23 +769,35 @@ async fn process_record_get(
     record_str: &str,
     v2: bool,
 ) -> CliResult {
-    let record = Record::try_from_str(record_str)?;
+    let content = match record::RecordKey::new(record_str) {
+        record::RecordKey::Known(record) => {
+            let key = record::get_record_key(
+                domain,
+                record,
+                if v2 {
+                    record::RecordVersion::V2
+                } else {
+                    record::RecordVersion::V1
+                },
+            )?;
+            match resolve::resolve_name_registry(rpc_client, &key).await? {
+                Some((header, data)) => Some(
+                    record::record_v1::deserialize_record(&data, record, &key, &header.owner)?
+                        .to_string(),
+                ),
+                None => None,
+            }
+        }
+        // Custom records are displayed as UTF-8
+        custom => sns_sdk::non_blocking::record::get_raw_record(rpc_client, domain, custom)
+            .await?
+            .map(|(_, data)| String::from_utf8_lossy(&data).into_owned()),
+    };
 
-    let key = record::get_record_key(
-        domain,
-        Record::try_from_str(record_str)?,
-        if v2 {
-            record::RecordVersion::V2
-        } else {
-            record::RecordVersion::V1
-        },
-    )?;
     let mut table = Table::new();
-    if let Some((_, data)) = resolve::resolve_name_registry(rpc_client, &key).await? {
-        let des = record::record_v1::deserialize_record(&data, record, &key)?;
-
+    if let Some(content) = content {
         table.add_row(row!["Domain", "Record", "Content"]);
-        table.add_row(row![format_domain(domain), record_str, des]);
+        table.add_row(row![format_domain(domain), record_str, content]);
     }
     Term::stdout().clear_to_end_of_screen()?;
     table.printstd();
@@ -768,22 +846,14 @@ pub async fn process_system_dump(rpc_client: &RpcClient) -> CliResult {
     eprintln!("From a total of {} domains", by_parent.keys().len());
     let reverse_lookup_keys =
         sns_sdk::non_blocking::resolve::resolve_reverse_batch(rpc_client, &parent_domains).await?;
-    for (domain, name) in parent_domains
-        .into_iter()
-        .zip(reverse_lookup_keys.into_iter())
-    {
+    for (domain, name) in parent_domains.into_iter().zip(reverse_lookup_keys) {
         if name.is_none() {
             continue;
         }
         let name = name.unwrap();
-        use Record::*;
-        let possible_records = [
-            Ipfs, Arwv, Sol, Eth, Btc, Ltc, Doge, Email, Url, Discord, Github, Reddit, Twitter,
-            Telegram, Pic, Shdw, Point, Bsc, Injective, Backpack, A, AAAA, CNAME, TXT, BASE,
-        ]
-        .into_iter()
-        .map(|r| get_record_v2_key(&name, r).map(|res| (res, r)))
-        .collect::<Result<HashMap<_, _>, _>>()?;
+        let possible_records = Record::all()
+            .map(|r| get_record_v2_key(&name, r).map(|res| (res, r)))
+            .collect::<Result<HashMap<_, _>, _>>()?;
         for record in by_parent.get(&domain).unwrap() {
             let record_type = possible_records.get(record);
             println!(
@@ -821,7 +891,22 @@ async fn main() {
         Commands::ReverseLookup { key, url } => {
             process_reverse_lookup(&get_rpc_client(url), &key).await
         }
-        Commands::Bridge { .. } => unimplemented!(),
+        Commands::Bridge {
+            target_chain,
+            domain,
+            keypair_path,
+            dry_run,
+            url,
+        } => {
+            process_bridge(
+                &get_rpc_client(url),
+                &target_chain,
+                &domain,
+                &keypair_path,
+                dry_run,
+            )
+            .await
+        }
         Commands::Register {
             domains,
             keypair_path,
@@ -840,15 +925,31 @@ async fn main() {
                 record,
                 content,
                 keypair,
+                no_validate,
             } => {
                 if v2 {
-                    unimplemented!()
+                    process_record_v2_set(
+                        &get_rpc_client(url),
+                        &domain,
+                        &record,
+                        &content,
+                        &keypair,
+                        no_validate,
+                    )
+                    .await
                 } else {
-                    process_record_set(&get_rpc_client(url), &domain, &record, &content, &keypair)
-                        .await
+                    process_record_set(
+                        &get_rpc_client(url),
+                        &domain,
+                        &record,
+                        &content,
+                        &keypair,
+                        no_validate,
+                    )
+                    .await
                 }
             }
-            RecordSubCommand::SystemDump {} => process_system_dump(&get_rpc_client(url)).await,
+            RecordSubCommand::SystemDump => process_system_dump(&get_rpc_client(url)).await,
         },
     };
 
@@ -1,2 +1,37 @@
+//! Blocking versions of the `non_blocking` API
+//!
+//! Every function drives its `non_blocking` counterpart to completion on the current thread, so
+//! it only accepts a `BlockingFetcher` i.e a fetcher that does not need an async runtime such as
+//! the blocking `RpcClient` or the `InMemoryFetcher`.
+
+/// Defines a blocking wrapper around the `non_blocking` function of the same name
+macro_rules! blocking {
+    ($(#[$attr:meta])* $module:ident, $name:ident($($arg:ident: $ty:ty),* $(,)?) -> $ret:ty) => {
+        #[doc = concat!(
+            "Blocking version of [`crate::non_blocking::", stringify!($module), "::",
+            stringify!($name), "`]"
+        )]
+        $(#[$attr])*
+        pub fn $name($($arg: $ty),*) -> $ret {
+            futures::executor::block_on(crate::non_blocking::$module::$name($($arg),*))
+        }
+    };
+}
+
+pub mod record;
 pub mod register;
 pub mod resolve;
+
+#[cfg(test)]
+mod tests {
+    use solana_program::pubkey::Pubkey;
+
+    use crate::{blocking::resolve::resolve_owner, test_utils::LedgerBuilder};
+
+    #[test]
+    fn in_memory() {
+        let owner = Pubkey::new_unique();
+        let fetcher = LedgerBuilder::new().domain("bonfida", owner).build();
+        assert_eq!(resolve_owner(&fetcher, "bonfida").unwrap(), Some(owner));
+    }
+}
@@ -0,0 +1,70 @@
+use solana_program::{instruction::Instruction, pubkey::Pubkey};
+use solana_sdk::signer::Signer;
+
+use crate::{
+    error::SnsError,
+    fetcher::BlockingFetcher,
+    record::{
+        record_v2::RecordVerification, Record, RecordKey, RecordVersion, RecordsBatch,
+        ResolvedRecord,
+    },
+};
+
+blocking!(
+    record,
+    get_record(
+        fetcher: &(impl BlockingFetcher + ?Sized),
+        domain: &str,
+        record: Record,
+    ) -> Result<Option<ResolvedRecord>, SnsError>
+);
+
+blocking!(
+    record,
+    get_records_batch(
+        fetcher: &(impl BlockingFetcher + ?Sized),
+        domains: &[&str],
+        records: &[Record],
+    ) -> Result<RecordsBatch, SnsError>
+);
+
+blocking!(
+    record,
+    get_record_v2_verification(
+        fetcher: &(impl BlockingFetcher + ?Sized),
+        domain: &str,
+        record: Record,
+    ) -> Result<Option<RecordVerification>, SnsError>
+);
+
+blocking!(
+    record,
+    get_raw_record(
+        fetcher: &(impl BlockingFetcher + ?Sized),
+        domain: &str,
+        record: impl Into<RecordKey>,
+    ) -> Result<Option<(RecordVersion, Vec<u8>)>, SnsError>
+);
+
+blocking!(
+    record,
+    set_record_v1(
+        fetcher: &(impl BlockingFetcher + ?Sized),
+        domain: &str,
+        record: impl Into<RecordKey>,
+        data: Vec<u8>,
+        owner: &Pubkey,
+        payer: &Pubkey,
+    ) -> Result<Vec<Instruction>, SnsError>
+);
+
+blocking!(
+    record,
+    set_sol_record_v1(
+        fetcher: &(impl BlockingFetcher + ?Sized),
+        domain: &str,
+        content: &Pubkey,
+        signer: &(impl Signer + ?Sized),
+        payer: &Pubkey,
+    ) -> Result<Vec<Instruction>, SnsError>
+);
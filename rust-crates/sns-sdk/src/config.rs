@@ -0,0 +1,184 @@
+use solana_program::{pubkey, pubkey::Pubkey};
+use std::borrow::Cow;
+
+use crate::{
+    derivation,
+    record::{record_v2::GUARDIANS, Record},
+    register,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Cluster {
+    Mainnet,
+    Devnet,
+    /// Another deployment, e.g on a local validator
+    Custom(Box<SnsConfig>),
+}
+
+/// The program IDs and accounts of an SNS deployment
+///
+/// `SnsConfig::MAINNET` and `SnsConfig::DEVNET` are the presets of the public clusters, other
+/// deployments (e.g a local validator) can be described with struct update syntax and used as
+/// `Cluster::Custom`:
+///
+/// ```
+/// # use sns_sdk::config::SnsConfig;
+/// # use solana_program::pubkey::Pubkey;
+/// let localnet = SnsConfig {
+///     root_domain_account: Pubkey::new_unique(),
+///     ..SnsConfig::MAINNET
+/// };
+/// ```
+///
+/// The free functions of the SDK use `SnsConfig::DEFAULT`, which is the devnet preset when the
+/// `devnet` feature is enabled and the mainnet preset otherwise.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SnsConfig {
+    pub root_domain_account: Pubkey,
+    pub reverse_lookup_class: Pubkey,
+    pub name_tokenizer_id: Pubkey,
+    pub name_offers_program_id: Pubkey,
+    pub register_program_id: Pubkey,
+    pub vault_owner: Pubkey,
+    /// The default mint used to pay for registrations
+    pub usdc_mint: Pubkey,
+    pub pyth_mapping_account: Pubkey,
+    /// (Mint, Pyth price account, Pyth product account) of the mints accepted for registrations
+    pub pyth_price_product_accounts: &'static [(Pubkey, Pubkey, Pubkey)],
+    pub referrers: &'static [Pubkey],
+    /// The guardians trusted to validate the right of association of records V2, by record
+    pub guardians: Cow<'static, [(Record, Cow<'static, [Pubkey]>)]>,
+    /// The warp program bridging domains to other chains, which is required to resolve the owner
+    /// of XChain-owned domains
+    ///
+    /// The presets leave it unset as the program ID is not published with `sns-warp-common`, so
+    /// cross-chain owner resolution fails with `SnsError::WarpProgramNotSet` until it is. Set it
+    /// with struct update syntax, e.g.
+    /// `SnsConfig { warp_program_id: Some(id), ..SnsConfig::MAINNET }`.
+    pub warp_program_id: Option<Pubkey>,
+    /// The emitter of the warp program, which owns the name registry of XChain-owned domains
+    pub warp_emitter: Pubkey,
+}
+
+impl SnsConfig {
+    pub const MAINNET: SnsConfig = SnsConfig {
+        root_domain_account: derivation::mainnet::ROOT_DOMAIN_ACCOUNT,
+        reverse_lookup_class: derivation::mainnet::REVERSE_LOOKUP_CLASS,
+        name_tokenizer_id: derivation::mainnet::NAME_TOKENIZER_ID,
+        name_offers_program_id: pubkey!("85iDfUvr3HJyLM2zcq5BXSiDvUWfw6cSE1FfNBo8Ap29"),
+        register_program_id: register::mainnet::REGISTER_PROGRAM_ID,
+        vault_owner: register::mainnet::VAULT_OWNER,
+        usdc_mint: register::mainnet::USDC_MINT,
+        pyth_mapping_account: register::mainnet::PYTH_MAPPING_ACC,
+        pyth_price_product_accounts: &register::mainnet::PYTH_PRICE_PRODUCT_ACCOUNTS,
+        referrers: &register::mainnet::REFERRERS,
+        guardians: Cow::Borrowed(&GUARDIANS),
+        warp_program_id: None,
+        warp_emitter: pubkey!("Gd9yhiRgrggdz2bJfntC2H1sRwzuzDssdhEmUFrC1STF"),
+    };
+
+    pub const DEVNET: SnsConfig = SnsConfig {
+        root_domain_account: derivation::devnet::ROOT_DOMAIN_ACCOUNT,
+        reverse_lookup_class: derivation::devnet::REVERSE_LOOKUP_CLASS,
+        name_tokenizer_id: derivation::devnet::NAME_TOKENIZER_ID,
+        name_offers_program_id: pubkey!("nameaSUMPQLdPzSimWStRKQyuwwiKscgWnZ6FSsT4zn"),
+        register_program_id: register::devnet::REGISTER_PROGRAM_ID,
+        vault_owner: register::devnet::VAULT_OWNER,
+        usdc_mint: register::devnet::USDC_MINT,
+        pyth_mapping_account: register::devnet::PYTH_MAPPING_ACC,
+        pyth_price_product_accounts: &register::devnet::PYTH_PRICE_PRODUCT_ACCOUNTS,
+        referrers: &register::devnet::REFERRERS,
+        // No devnet guardian is published, see `GUARDIANS`
+        guardians: Cow::Borrowed(&GUARDIANS),
+        warp_program_id: None,
+        warp_emitter: pubkey!("2LwUiaPAXhaiMmdRjkSHeSfqmpEMDZ6cLz9myDeKUtsP"),
+    };
+
+    #[cfg(not(feature = "devnet"))]
+    pub const DEFAULT: SnsConfig = SnsConfig::MAINNET;
+
+    #[cfg(feature = "devnet")]
+    pub const DEFAULT: SnsConfig = SnsConfig::DEVNET;
+
+    pub fn for_cluster(cluster: Cluster) -> Self {
+        match cluster {
+            Cluster::Mainnet => Self::MAINNET,
+            Cluster::Devnet => Self::DEVNET,
+            Cluster::Custom(config) => *config,
+        }
+    }
+
+    /// The guardians trusted to validate the right of association of `record`, empty when the
+    /// record validates it with its content
+    pub fn get_guardians(&self, record: Record) -> &[Pubkey] {
+        self.guardians
+            .iter()
+            .find(|(r, _)| *r == record)
+            .map_or(&[], |(_, guardians)| guardians)
+    }
+}
+
+impl Default for SnsConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl From<Cluster> for SnsConfig {
+    fn from(cluster: Cluster) -> Self {
+        Self::for_cluster(cluster)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets() {
+        assert_eq!(SnsConfig::from(Cluster::Mainnet), SnsConfig::MAINNET);
+        assert_eq!(SnsConfig::from(Cluster::Devnet), SnsConfig::DEVNET);
+        assert_eq!(SnsConfig::default(), SnsConfig::DEFAULT);
+
+        let localnet = SnsConfig {
+            root_domain_account: Pubkey::new_unique(),
+            ..SnsConfig::DEVNET
+        };
+        assert_eq!(
+            SnsConfig::from(Cluster::Custom(Box::new(localnet.clone()))),
+            localnet
+        );
+
+        let mainnet = SnsConfig::MAINNET.get_domain_key("bonfida").unwrap();
+        let devnet = SnsConfig::DEVNET.get_domain_key("bonfida").unwrap();
+        assert_eq!(
+            mainnet.to_string(),
+            "Crf8hzfthWGbGbLTVCiqRqV5MVnbpHB1L9KQMd6gsinb"
+        );
+        assert_ne!(mainnet, devnet);
+        assert_ne!(
+            SnsConfig::MAINNET.get_reverse_key("bonfida").unwrap(),
+            SnsConfig::DEVNET.get_reverse_key("bonfida").unwrap()
+        );
+    }
+
+    #[test]
+    fn guardians() {
+        use crate::record::record_v2::GUARDIAN_ID;
+
+        assert_eq!(SnsConfig::MAINNET.get_guardians(Record::Url), [GUARDIAN_ID]);
+        assert!(SnsConfig::MAINNET.get_guardians(Record::Sol).is_empty());
+
+        // Guardians known at runtime
+        let own_guardian = Pubkey::new_unique();
+        let config = SnsConfig {
+            guardians: vec![(Record::Url, vec![GUARDIAN_ID, own_guardian].into())].into(),
+            ..SnsConfig::MAINNET
+        };
+        assert_eq!(
+            config.get_guardians(Record::Url),
+            [GUARDIAN_ID, own_guardian]
+        );
+        assert!(config.get_guardians(Record::CNAME).is_empty());
+    }
+}
@@ -3,11 +3,15 @@ use {
     spl_name_service::state::{get_seeds_and_key, HASH_PREFIX},
 };
 
-use crate::{error::SnsError, record::RecordVersion};
+use crate::{config::SnsConfig, error::SnsError, record::RecordVersion};
 
-pub use constants::*;
 #[cfg(not(feature = "devnet"))]
-mod constants {
+pub use mainnet::*;
+
+#[cfg(feature = "devnet")]
+pub use devnet::*;
+
+pub mod mainnet {
     use super::*;
 
     pub const ROOT_DOMAIN_ACCOUNT: Pubkey = pubkey!("58PwtjSDuFHuUkYjH9BYnnQKHfwo9reZhC2zMJv9JPkx");
@@ -17,8 +21,8 @@ mod constants {
     pub const MINT_PREFIX: &[u8; 14] = b"tokenized_name";
     pub const NAME_TOKENIZER_ID: Pubkey = pubkey!("nftD3vbNkNqfj2Sd3HZwbpw4BxxKWr4AjGb9X38JeZk");
 }
-#[cfg(feature = "devnet")]
-mod constants {
+
+pub mod devnet {
     use super::*;
 
     pub const ROOT_DOMAIN_ACCOUNT: Pubkey = pubkey!("5eoDkP6vCQBXqDV9YN2NdUs3nmML3dMRNmEYpiyVNBm2");
@@ -64,14 +68,7 @@ pub fn derive(domain: &str, parent: &Pubkey, name_class: Option<Pubkey>) -> Pubk
 }
 
 pub fn derive_reverse(domain_key: &Pubkey, parent: Option<&Pubkey>) -> Pubkey {
-    let hashed = get_hashed_name(&domain_key.to_string());
-    let (key, _) = get_seeds_and_key(
-        &spl_name_service::ID,
-        hashed,
-        Some(&REVERSE_LOOKUP_CLASS),
-        parent,
-    );
-    key
+    SnsConfig::DEFAULT.derive_reverse(domain_key, parent)
 }
 
 pub fn trim_tld(domain: &str) -> &str {
@@ -80,74 +77,135 @@ pub fn trim_tld(domain: &str) -> &str {
 
 #[inline(always)]
 pub fn get_domain_key(domain: &str) -> Result<Pubkey, SnsError> {
-    get_domain_key_with_parent(domain).map(|d| d.key)
+    SnsConfig::DEFAULT.get_domain_key(domain)
 }
+
 pub struct DomainKeyWithParent {
     pub key: Pubkey,
     pub parent: Pubkey,
 }
+
+/// A label of a domain name along with its derived key and the key of its parent
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DerivedLabel {
+    pub label: String,
+    pub key: Pubkey,
+    pub parent: Pubkey,
+}
+
+/// Derives every label of a domain, starting from the top level domain down to the
+/// left-most label (e.g `bonfida`, `dex.bonfida` then `v2.dex.bonfida` for `v2.dex.bonfida.sol`)
+pub fn get_domain_chain(domain: &str) -> Result<Vec<DerivedLabel>, SnsError> {
+    SnsConfig::DEFAULT.get_domain_chain(domain)
+}
+
 pub fn get_domain_key_with_parent(domain: &str) -> Result<DomainKeyWithParent, SnsError> {
-    let domain = trim_tld(domain);
-    let splitted = domain.split('.').collect::<Vec<_>>();
-    match splitted.len() {
-        1 => {
-            let key = derive(domain, &ROOT_DOMAIN_ACCOUNT, None);
-            Ok(DomainKeyWithParent {
-                key,
-                parent: ROOT_DOMAIN_ACCOUNT,
-            })
-        }
-        2 => {
-            let parent = derive(splitted[1], &ROOT_DOMAIN_ACCOUNT, None);
-            let sub_domain = get_prefix(Domain::Sub) + splitted[0];
-            let key = derive(&sub_domain, &parent, None);
-            Ok(DomainKeyWithParent { key, parent })
-        }
-        // 3 => {
-        //     let parent = derive(splitted[2], &ROOT_DOMAIN_ACCOUNT);
-        //     let sub_domain = get_prefix(Domain::Sub) + splitted[1];
-        //     let sub_key = derive(&sub_domain, &parent);
-        //     let record = get_prefix(Domain::Record(record)) + splitted[0];
-        //     let key = derive(&record, &sub_key);
-        //     Ok(key)
-        // }
-        _ => Err(SnsError::InvalidDomain),
-    }
+    SnsConfig::DEFAULT.get_domain_key_with_parent(domain)
 }
 
 pub fn get_reverse_key(domain: &str) -> Result<Pubkey, SnsError> {
-    let domain = trim_tld(domain);
-    let splitted = domain.split('.').collect::<Vec<_>>();
-    match splitted.len() {
-        1 => {
-            let domain_key = get_domain_key(domain)?;
-            let hashed = get_hashed_name(&domain_key.to_string());
-            let (key, _) = get_seeds_and_key(
-                &spl_name_service::ID,
-                hashed,
-                Some(&REVERSE_LOOKUP_CLASS),
-                None,
-            );
-            Ok(key)
-        }
-        2 => {
-            let parent_key = get_domain_key(splitted[1])?;
-            let domain_key = get_domain_key(domain)?;
-            let hashed = get_hashed_name(&domain_key.to_string());
-            let (key, _) = get_seeds_and_key(
-                &spl_name_service::ID,
-                hashed,
-                Some(&REVERSE_LOOKUP_CLASS),
-                Some(&parent_key),
-            );
-            Ok(key)
-        }
-        _ => Err(SnsError::InvalidDomain),
-    }
+    SnsConfig::DEFAULT.get_reverse_key(domain)
 }
 
 pub fn get_domain_mint(domain_key: &Pubkey) -> Pubkey {
-    Pubkey::find_program_address(&[MINT_PREFIX, &domain_key.to_bytes()], &NAME_TOKENIZER_ID).0
+    SnsConfig::DEFAULT.get_domain_mint(domain_key)
+}
+
+/// Derives the `XDomain` account which holds the owner of a domain bridged by the warp program
+pub fn get_x_domain_key(domain_key: &Pubkey, warp_program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[domain_key.as_ref()], warp_program_id).0
+}
+
+/// Derivations against the accounts of a given cluster
+impl SnsConfig {
+    #[inline(always)]
+    pub fn get_domain_key(&self, domain: &str) -> Result<Pubkey, SnsError> {
+        self.get_domain_key_with_parent(domain).map(|d| d.key)
+    }
+
+    /// See [`get_domain_chain`]
+    pub fn get_domain_chain(&self, domain: &str) -> Result<Vec<DerivedLabel>, SnsError> {
+        let domain = trim_tld(domain);
+        let mut chain: Vec<DerivedLabel> = Vec::new();
+        for label in domain.rsplit('.') {
+            if label.is_empty() {
+                return Err(SnsError::InvalidDomain);
+            }
+            let (name, parent) = match chain.last() {
+                Some(p) => (get_prefix(Domain::Sub) + label, p.key),
+                None => (label.to_owned(), self.root_domain_account),
+            };
+            chain.push(DerivedLabel {
+                label: label.to_owned(),
+                key: derive(&name, &parent, None),
+                parent,
+            });
+        }
+        Ok(chain)
+    }
+
+    /// Returns the last label of the chain i.e the domain itself
+    fn get_leaf(&self, domain: &str) -> Result<DerivedLabel, SnsError> {
+        self.get_domain_chain(domain)?
+            .pop()
+            .ok_or(SnsError::InvalidDomain)
+    }
+
+    pub fn get_domain_key_with_parent(
+        &self,
+        domain: &str,
+    ) -> Result<DomainKeyWithParent, SnsError> {
+        let DerivedLabel { key, parent, .. } = self.get_leaf(domain)?;
+        Ok(DomainKeyWithParent { key, parent })
+    }
+
+    pub fn get_reverse_key(&self, domain: &str) -> Result<Pubkey, SnsError> {
+        let leaf = self.get_leaf(domain)?;
+        // Reverse accounts of subdomains are parented by the parent domain
+        let parent = (leaf.parent != self.root_domain_account).then_some(&leaf.parent);
+        Ok(self.derive_reverse(&leaf.key, parent))
+    }
+
+    pub fn derive_reverse(&self, domain_key: &Pubkey, parent: Option<&Pubkey>) -> Pubkey {
+        let hashed = get_hashed_name(&domain_key.to_string());
+        let (key, _) = get_seeds_and_key(
+            &spl_name_service::ID,
+            hashed,
+            Some(&self.reverse_lookup_class),
+            parent,
+        );
+        key
+    }
+
+    pub fn get_domain_mint(&self, domain_key: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(
+            &[MINT_PREFIX, &domain_key.to_bytes()],
+            &self.name_tokenizer_id,
+        )
+        .0
+    }
+
+    /// See [`get_x_domain_key`], `None` when `warp_program_id` is not set
+    pub fn get_x_domain_key(&self, domain_key: &Pubkey) -> Option<Pubkey> {
+        self.warp_program_id
+            .map(|program_id| get_x_domain_key(domain_key, &program_id))
+    }
+
+    /// Derives the key of an account attached to a domain (e.g a record) with the given prefix
+    pub(crate) fn derive_child(
+        &self,
+        domain: &str,
+        prefix: Domain,
+        name: &str,
+        name_class: Option<Pubkey>,
+    ) -> Result<Pubkey, SnsError> {
+        let leaf = self.get_leaf(domain)?;
+        Ok(derive(
+            &format!("{}{name}", get_prefix(prefix)),
+            &leaf.key,
+            name_class,
+        ))
+    }
 }
 
 #[cfg(test)]
@@ -172,4 +230,50 @@ mod tests {
         let expected: Pubkey = pubkey!("HoFfFXqFHAC8RP3duuQNzag1ieUwJRBv1HtRNiWFq4Qu");
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn domain_chain() {
+        let chain = get_domain_chain("v2.dex.bonfida.sol").unwrap();
+        assert_eq!(
+            chain.iter().map(|l| l.label.as_str()).collect::<Vec<_>>(),
+            vec!["bonfida", "dex", "v2"]
+        );
+        assert_eq!(chain[0].parent, ROOT_DOMAIN_ACCOUNT);
+        assert_eq!(
+            chain[1].key,
+            pubkey!("HoFfFXqFHAC8RP3duuQNzag1ieUwJRBv1HtRNiWFq4Qu")
+        );
+        assert_eq!(chain[2].parent, chain[1].key);
+        assert_eq!(
+            chain[2].key,
+            derive(&(get_prefix(Domain::Sub) + "v2"), &chain[1].key, None)
+        );
+        assert_eq!(get_domain_key("v2.dex.bonfida").unwrap(), chain[2].key);
+
+        assert!(get_domain_chain("dex..bonfida").is_err());
+        assert!(get_domain_chain("").is_err());
+    }
+
+    #[test]
+    fn reverse_key() {
+        let key = get_domain_key("bonfida").unwrap();
+        assert_eq!(
+            get_reverse_key("bonfida").unwrap(),
+            derive_reverse(&key, None)
+        );
+
+        let parent = get_domain_key("bonfida").unwrap();
+        let key = get_domain_key("dex.bonfida").unwrap();
+        assert_eq!(
+            get_reverse_key("dex.bonfida").unwrap(),
+            derive_reverse(&key, Some(&parent))
+        );
+
+        let parent = key;
+        let key = get_domain_key("v2.dex.bonfida").unwrap();
+        assert_eq!(
+            get_reverse_key("v2.dex.bonfida").unwrap(),
+            derive_reverse(&key, Some(&parent))
+        );
+    }
 }
@@ -0,0 +1,288 @@
+use std::{fmt, ops::Deref, str::FromStr};
+
+use derive_more::{Display, Error};
+
+use crate::error::SnsError;
+
+pub const TLD: &str = "sol";
+
+/// The maximum number of characters of a single label
+pub const MAX_LABEL_LENGTH: usize = 63;
+
+#[derive(Clone, Debug, PartialEq, Eq, Display)]
+pub enum LabelErrorKind {
+    #[display(fmt = "label is empty")]
+    Empty,
+    #[display(fmt = "label is longer than {} characters", MAX_LABEL_LENGTH)]
+    TooLong,
+    #[display(fmt = "invalid character {:?}", _0)]
+    InvalidCharacter(char),
+    #[display(fmt = "label could not be normalized")]
+    Normalization,
+}
+
+/// Describes which label of a domain failed validation and why
+#[derive(Clone, Debug, PartialEq, Eq, Display, Error)]
+#[display(fmt = "invalid label {:?}: {}", label, kind)]
+pub struct InvalidLabel {
+    pub label: String,
+    pub kind: LabelErrorKind,
+}
+
+/// A normalized and validated .sol domain name (e.g `dex.bonfida.sol`)
+///
+/// Parsing strips the `.sol` suffix, lowercases ASCII, maps fullwidth characters to ASCII and
+/// decodes punycode labels so that equivalent inputs (`Bonfida.SOL`, `ｂｏｎｆｉｄａ`, `xn--ls8h`)
+/// derive the same keys. A `DomainName` dereferences to the normalized name without the TLD and can
+/// be passed wherever a `&str` domain is expected.
+///
+/// Normalization is deliberately limited to ASCII: no UTS #46 mapping is applied. Names are hashed
+/// on-chain from their exact codepoints, so mapping non-ASCII characters would derive keys of
+/// domains other than the ones registered. Non-ASCII characters other than whitespace and control
+/// characters are therefore kept unchanged, which means that uppercase non-ASCII letters (`Ü`) and
+/// confusables (the Cyrillic `а`) are accepted and derive different keys than their lowercase or
+/// Latin lookalikes. Applications displaying names to users should flag such labels themselves.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DomainName {
+    name: String,
+    labels: Vec<String>,
+}
+
+impl DomainName {
+    pub fn parse(input: &str) -> Result<Self, SnsError> {
+        let input = strip_tld(input);
+        let labels = input
+            .split('.')
+            .map(normalize_label)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            name: labels.join("."),
+            labels,
+        })
+    }
+
+    /// The labels of the domain, from left to right (e.g `["dex", "bonfida"]`)
+    pub fn labels(&self) -> &[String] {
+        &self.labels
+    }
+
+    /// The parent of a subdomain, `None` for a top level domain
+    pub fn parent(&self) -> Option<DomainName> {
+        let labels = self.labels.get(1..).filter(|l| !l.is_empty())?.to_vec();
+        Some(Self {
+            name: labels.join("."),
+            labels,
+        })
+    }
+
+    pub fn is_subdomain(&self) -> bool {
+        self.labels.len() > 1
+    }
+
+    pub fn tld(&self) -> &'static str {
+        TLD
+    }
+
+    /// The normalized name without the TLD (e.g `dex.bonfida`)
+    pub fn as_str(&self) -> &str {
+        &self.name
+    }
+}
+
+fn strip_tld(input: &str) -> &str {
+    let suffix_start = input.len().saturating_sub(TLD.len() + 1);
+    match input.get(suffix_start..) {
+        Some(suffix) if suffix.starts_with('.') && suffix[1..].eq_ignore_ascii_case(TLD) => {
+            &input[..suffix_start]
+        }
+        _ => input,
+    }
+}
+
+fn normalize_label(label: &str) -> Result<String, InvalidLabel> {
+    let err = |kind| InvalidLabel {
+        label: label.to_owned(),
+        kind,
+    };
+    if label.is_empty() {
+        return Err(err(LabelErrorKind::Empty));
+    }
+    let normalized = match label.get(..4) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("xn--") => {
+            idna::punycode::decode_to_string(&label[4..].to_ascii_lowercase())
+                .ok_or_else(|| err(LabelErrorKind::Normalization))?
+        }
+        _ => label.chars().map(normalize_char).collect::<String>(),
+    };
+    if let Some(c) = normalized.chars().find(|c| !is_valid_char(*c)) {
+        return Err(err(LabelErrorKind::InvalidCharacter(c)));
+    }
+    match normalized.chars().count() {
+        0 => Err(err(LabelErrorKind::Empty)),
+        n if n > MAX_LABEL_LENGTH => Err(err(LabelErrorKind::TooLong)),
+        _ => Ok(normalized),
+    }
+}
+
+/// Lowercases ASCII and maps fullwidth ASCII variants to ASCII, other characters are kept as is
+/// since names are hashed from their exact codepoints (e.g the U+FE0F variation selector of
+/// emoji domains)
+fn normalize_char(c: char) -> char {
+    let c = match c {
+        '\u{ff01}'..='\u{ff5e}' => char::from_u32(c as u32 - 0xff01 + 0x21).unwrap_or(c),
+        _ => c,
+    };
+    c.to_ascii_lowercase()
+}
+
+fn is_valid_char(c: char) -> bool {
+    if c.is_ascii() {
+        c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_'
+    } else {
+        !c.is_whitespace() && !c.is_control()
+    }
+}
+
+impl FromStr for DomainName {
+    type Err = SnsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl TryFrom<&str> for DomainName {
+    type Error = SnsError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Self::parse(value)
+    }
+}
+
+impl fmt::Display for DomainName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.name, TLD)
+    }
+}
+
+impl Deref for DomainName {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.name
+    }
+}
+
+impl AsRef<str> for DomainName {
+    fn as_ref(&self) -> &str {
+        &self.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::derivation::get_domain_key;
+
+    #[test]
+    fn parse() {
+        let domain = DomainName::parse("Dex.Bonfida.SOL").unwrap();
+        assert_eq!(domain.as_str(), "dex.bonfida");
+        assert_eq!(domain.labels(), ["dex", "bonfida"]);
+        assert_eq!(domain.parent().unwrap().as_str(), "bonfida");
+        assert!(domain.parent().unwrap().parent().is_none());
+        assert!(domain.is_subdomain());
+        assert_eq!(domain.to_string(), "dex.bonfida.sol");
+        assert_eq!(domain.tld(), "sol");
+
+        assert_eq!(DomainName::parse("🇺🇸.sol").unwrap().as_str(), "🇺🇸");
+        assert_eq!(DomainName::parse("xn--ls8h").unwrap().as_str(), "💩");
+        assert_eq!(
+            DomainName::parse("ｂｏｎｆｉｄａ").unwrap().as_str(),
+            "bonfida"
+        );
+        assert_eq!(
+            DomainName::parse("my_domain-1").unwrap().as_str(),
+            "my_domain-1"
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let check = |input: &str, label: &str, kind: LabelErrorKind| match DomainName::parse(input)
+        {
+            Err(SnsError::InvalidDomainLabel(e)) => {
+                assert_eq!(e.label, label);
+                assert_eq!(e.kind, kind);
+            }
+            r => panic!("Unexpected result for {input}: {r:?}"),
+        };
+        check("dex..bonfida", "", LabelErrorKind::Empty);
+        check(".sol", "", LabelErrorKind::Empty);
+        check(
+            "my domain",
+            "my domain",
+            LabelErrorKind::InvalidCharacter(' '),
+        );
+        check("a/b.bonfida", "a/b", LabelErrorKind::InvalidCharacter('/'));
+        check(
+            "bon\u{a0}fida",
+            "bon\u{a0}fida",
+            LabelErrorKind::InvalidCharacter('\u{a0}'),
+        );
+        check(
+            "bon\u{3000}fida",
+            "bon\u{3000}fida",
+            LabelErrorKind::InvalidCharacter('\u{3000}'),
+        );
+        check(
+            "bon\u{85}fida",
+            "bon\u{85}fida",
+            LabelErrorKind::InvalidCharacter('\u{85}'),
+        );
+        check(
+            "xn--99999999",
+            "xn--99999999",
+            LabelErrorKind::Normalization,
+        );
+        let long = "a".repeat(MAX_LABEL_LENGTH + 1);
+        check(&long, &long, LabelErrorKind::TooLong);
+    }
+
+    #[test]
+    fn non_ascii_passthrough() {
+        // Only ASCII is case folded, other characters are kept as is
+        for input in ["Ünicode", "ünicode", "ΣΝΣ", "bonfidа", "ﬁ", "ǅ"] {
+            assert_eq!(DomainName::parse(input).unwrap().as_str(), input);
+        }
+
+        // Lookalikes are distinct domains
+        let cyrillic = DomainName::parse("bonfidа").unwrap();
+        assert_ne!(cyrillic, DomainName::parse("bonfida").unwrap());
+        assert_ne!(
+            get_domain_key(&cyrillic).unwrap(),
+            get_domain_key("bonfida").unwrap()
+        );
+        assert_ne!(
+            get_domain_key(&DomainName::parse("Ünicode").unwrap()).unwrap(),
+            get_domain_key(&DomainName::parse("ünicode").unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn derivation() {
+        let domain = DomainName::parse("Bonfida.sol").unwrap();
+        assert_eq!(
+            get_domain_key(&domain).unwrap(),
+            get_domain_key("bonfida").unwrap()
+        );
+
+        // Variation selectors are part of the name hashed on-chain
+        let domain = DomainName::parse("❤\u{fe0f}.sol").unwrap();
+        assert_eq!(domain.as_str(), "❤\u{fe0f}");
+        assert_ne!(
+            get_domain_key(&domain).unwrap(),
+            get_domain_key("❤").unwrap()
+        );
+    }
+}
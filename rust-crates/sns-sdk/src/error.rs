@@ -1,10 +1,13 @@
 use std::array::TryFromSliceError;
 
+use crate::domain_name::InvalidLabel;
+
 use {
     derive_more::{Display, Error},
     ed25519_dalek::ed25519,
     solana_client::client_error::ClientError,
     solana_program::program_error::ProgramError,
+    solana_sdk::signer::SignerError,
     std::string::FromUtf8Error,
 };
 
@@ -12,7 +15,7 @@ use {
 pub enum SnsError {
     #[display(fmt = "")]
     InvalidDomain,
-    SolanaClient(ClientError),
+    SolanaClient(Box<ClientError>),
     SolanaProgramError(ProgramError),
     InvalidReverse,
     ED25519(ed25519::Error),
@@ -28,20 +31,36 @@ pub enum SnsError {
     Punycode,
     InvalidEvmAddress,
     InvalidInjectiveAddress,
+    InvalidBitcoinAddress,
+    InvalidMoveAddress,
+    InvalidStellarAddress,
+    InvalidTonAddress,
+    InvalidUrl,
+    InvalidEmail,
+    InvalidIpfsCid,
+    InvalidArweaveId,
+    InvalidHandle,
+    RecordContentTooLong,
     InvalidIpv4,
     InvalidIpv6,
     SolRecordNotSupported,
     NftRecordDoesNotExist,
+    XDomainNotFound,
+    WarpProgramNotSet,
     Casting,
     TryFromSlice(std::array::TryFromSliceError),
     RecordsError(sns_records::error::SnsRecordsError),
     StaleRecord,
     UnverifiedRecord,
+    InvalidDomainLabel(InvalidLabel),
+    InvalidSignature,
+    InvalidRecordValue,
+    Signer(SignerError),
 }
 
 impl From<ClientError> for SnsError {
     fn from(e: ClientError) -> Self {
-        Self::SolanaClient(e)
+        Self::SolanaClient(Box::new(e))
     }
 }
 
@@ -98,3 +117,15 @@ impl From<sns_records::error::SnsRecordsError> for SnsError {
         Self::RecordsError(value)
     }
 }
+
+impl From<InvalidLabel> for SnsError {
+    fn from(value: InvalidLabel) -> Self {
+        Self::InvalidDomainLabel(value)
+    }
+}
+
+impl From<SignerError> for SnsError {
+    fn from(e: SignerError) -> Self {
+        Self::Signer(e)
+    }
+}
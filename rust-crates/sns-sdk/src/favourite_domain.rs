@@ -5,14 +5,20 @@ use borsh::BorshDeserialize;
 use solana_program::pubkey::Pubkey;
 use solana_sdk::instruction::Instruction;
 
-use crate::NAME_OFFERS_PROGRAM_ID;
+use crate::config::SnsConfig;
 
 pub fn derive_favourite_domain_key(owner: &Pubkey) -> Pubkey {
-    Pubkey::find_program_address(
-        &[b"favourite_domain", &owner.to_bytes()],
-        &NAME_OFFERS_PROGRAM_ID,
-    )
-    .0
+    SnsConfig::DEFAULT.derive_favourite_domain_key(owner)
+}
+
+impl SnsConfig {
+    pub fn derive_favourite_domain_key(&self, owner: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(
+            &[b"favourite_domain", &owner.to_bytes()],
+            &self.name_offers_program_id,
+        )
+        .0
+    }
 }
 
 #[derive(BorshDeserialize)]
@@ -46,6 +52,9 @@ pub mod register_favourite {
     use borsh::{BorshDeserialize, BorshSerialize};
     use solana_sdk::pubkey::Pubkey;
 
+    #[cfg(feature = "instruction_params_casting")]
+    use bytemuck::{Pod, Zeroable};
+
     #[derive(InstructionsAccount)]
     /// The required accounts for the `create` instruction
     pub struct Accounts<'a, T> {
@@ -0,0 +1,329 @@
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use {
+    solana_account_decoder::UiAccountEncoding,
+    solana_client::{
+        nonblocking::rpc_client::RpcClient,
+        rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+        rpc_filter::RpcFilterType,
+    },
+    solana_program::pubkey::Pubkey,
+    solana_sdk::account::{Account, AccountSharedData},
+};
+
+use crate::{config::SnsConfig, error::SnsError};
+
+/// The source of account data used by the SDK
+///
+/// It is implemented for the Solana `RpcClient` and for the `InMemoryFetcher`, and can be
+/// implemented on top of an indexer or a cache. Implementations that are not backed by an
+/// `RpcClient` can report their failures with
+/// `ClientError::from(ClientErrorKind::Custom(..))`.
+///
+/// The fetcher also tells which SNS deployment its accounts belong to, see `ClusterFetcher` to
+/// use a client with another cluster than `SnsConfig::DEFAULT`.
+#[async_trait]
+pub trait AccountFetcher: Send + Sync {
+    fn config(&self) -> &SnsConfig {
+        &SnsConfig::DEFAULT
+    }
+
+    async fn get_account(&self, key: &Pubkey) -> Result<Option<Account>, SnsError>;
+
+    /// Fetches a list of accounts, the result has the same length and order as `keys`
+    async fn get_multiple_accounts(
+        &self,
+        keys: &[Pubkey],
+    ) -> Result<Vec<Option<Account>>, SnsError>;
+
+    /// Fetches all the accounts owned by `program_id` matching every filter
+    async fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<(Pubkey, Account)>, SnsError>;
+}
+
+#[async_trait]
+impl AccountFetcher for RpcClient {
+    async fn get_account(&self, key: &Pubkey) -> Result<Option<Account>, SnsError> {
+        Ok(self
+            .get_account_with_commitment(key, self.commitment())
+            .await?
+            .value)
+    }
+
+    async fn get_multiple_accounts(
+        &self,
+        keys: &[Pubkey],
+    ) -> Result<Vec<Option<Account>>, SnsError> {
+        Ok(RpcClient::get_multiple_accounts(self, keys).await?)
+    }
+
+    async fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<(Pubkey, Account)>, SnsError> {
+        let config = RpcProgramAccountsConfig {
+            filters: Some(filters),
+            with_context: None,
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                ..Default::default()
+            },
+        };
+        Ok(self
+            .get_program_accounts_with_config(program_id, config)
+            .await?)
+    }
+}
+
+/// The maximum number of accounts of a `getMultipleAccounts` RPC request
+pub const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// Fetches any number of accounts in as few `get_multiple_accounts` calls as possible
+pub async fn get_multiple_accounts_chunked(
+    fetcher: &(impl AccountFetcher + ?Sized),
+    keys: &[Pubkey],
+) -> Result<Vec<Option<Account>>, SnsError> {
+    let mut res = Vec::with_capacity(keys.len());
+    for chunk in keys.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        res.extend(fetcher.get_multiple_accounts(chunk).await?);
+    }
+    Ok(res)
+}
+
+/// An `AccountFetcher` whose futures complete without yielding and don't need an async runtime,
+/// which can be used with the `blocking` API
+///
+/// The nonblocking `RpcClient` is not one, as it needs a Tokio reactor.
+pub trait BlockingFetcher: AccountFetcher {}
+
+/// Blocking client, its futures complete without yielding and don't need an async runtime
+#[cfg(feature = "blocking")]
+impl BlockingFetcher for solana_client::rpc_client::RpcClient {}
+
+#[cfg(feature = "blocking")]
+#[async_trait]
+impl AccountFetcher for solana_client::rpc_client::RpcClient {
+    async fn get_account(&self, key: &Pubkey) -> Result<Option<Account>, SnsError> {
+        Ok(self
+            .get_account_with_commitment(key, self.commitment())?
+            .value)
+    }
+
+    async fn get_multiple_accounts(
+        &self,
+        keys: &[Pubkey],
+    ) -> Result<Vec<Option<Account>>, SnsError> {
+        Ok(solana_client::rpc_client::RpcClient::get_multiple_accounts(
+            self, keys,
+        )?)
+    }
+
+    async fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<(Pubkey, Account)>, SnsError> {
+        let config = RpcProgramAccountsConfig {
+            filters: Some(filters),
+            with_context: None,
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                ..Default::default()
+            },
+        };
+        Ok(self.get_program_accounts_with_config(program_id, config)?)
+    }
+}
+
+/// Resolves against the deployment described by `config`
+///
+/// ```ignore
+/// let devnet = ClusterFetcher::new(RpcClient::new(devnet_url), Cluster::Devnet);
+/// let owner = resolve_owner(&devnet, "bonfida").await?;
+/// ```
+#[derive(Clone, Debug)]
+pub struct ClusterFetcher<F> {
+    pub fetcher: F,
+    pub config: SnsConfig,
+}
+
+impl<F: AccountFetcher> ClusterFetcher<F> {
+    pub fn new(fetcher: F, config: impl Into<SnsConfig>) -> Self {
+        Self {
+            fetcher,
+            config: config.into(),
+        }
+    }
+}
+
+impl<F: BlockingFetcher> BlockingFetcher for ClusterFetcher<F> {}
+
+#[async_trait]
+impl<F: AccountFetcher> AccountFetcher for ClusterFetcher<F> {
+    fn config(&self) -> &SnsConfig {
+        &self.config
+    }
+
+    async fn get_account(&self, key: &Pubkey) -> Result<Option<Account>, SnsError> {
+        self.fetcher.get_account(key).await
+    }
+
+    async fn get_multiple_accounts(
+        &self,
+        keys: &[Pubkey],
+    ) -> Result<Vec<Option<Account>>, SnsError> {
+        self.fetcher.get_multiple_accounts(keys).await
+    }
+
+    async fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<(Pubkey, Account)>, SnsError> {
+        self.fetcher.get_program_accounts(program_id, filters).await
+    }
+}
+
+/// An `AccountFetcher` serving a fixed set of accounts, e.g for tests or snapshots
+#[derive(Clone, Debug, Default)]
+pub struct InMemoryFetcher {
+    accounts: BTreeMap<Pubkey, Account>,
+    config: SnsConfig,
+}
+
+impl InMemoryFetcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(mut self, config: impl Into<SnsConfig>) -> Self {
+        self.config = config.into();
+        self
+    }
+
+    pub fn insert(&mut self, key: Pubkey, account: Account) -> Option<Account> {
+        self.accounts.insert(key, account)
+    }
+
+    pub fn remove(&mut self, key: &Pubkey) -> Option<Account> {
+        self.accounts.remove(key)
+    }
+
+    pub fn get(&self, key: &Pubkey) -> Option<&Account> {
+        self.accounts.get(key)
+    }
+}
+
+impl FromIterator<(Pubkey, Account)> for InMemoryFetcher {
+    fn from_iter<T: IntoIterator<Item = (Pubkey, Account)>>(iter: T) -> Self {
+        Self {
+            accounts: iter.into_iter().collect(),
+            config: SnsConfig::DEFAULT,
+        }
+    }
+}
+
+impl BlockingFetcher for InMemoryFetcher {}
+
+#[async_trait]
+impl AccountFetcher for InMemoryFetcher {
+    fn config(&self) -> &SnsConfig {
+        &self.config
+    }
+
+    async fn get_account(&self, key: &Pubkey) -> Result<Option<Account>, SnsError> {
+        Ok(self.accounts.get(key).cloned())
+    }
+
+    async fn get_multiple_accounts(
+        &self,
+        keys: &[Pubkey],
+    ) -> Result<Vec<Option<Account>>, SnsError> {
+        Ok(keys.iter().map(|k| self.accounts.get(k).cloned()).collect())
+    }
+
+    async fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> Result<Vec<(Pubkey, Account)>, SnsError> {
+        let res = self
+            .accounts
+            .iter()
+            .filter(|(_, acc)| acc.owner == *program_id)
+            .filter(|(_, acc)| {
+                let shared = AccountSharedData::from((*acc).clone());
+                filters.iter().all(|f| f.allows(&shared))
+            })
+            .map(|(k, acc)| (*k, acc.clone()))
+            .collect();
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_client::rpc_filter::Memcmp;
+
+    fn account(owner: Pubkey, data: Vec<u8>) -> Account {
+        Account {
+            lamports: 1,
+            data,
+            owner,
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn in_memory() {
+        let program_id = Pubkey::new_unique();
+        let (a, b, c) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let fetcher = InMemoryFetcher::from_iter([
+            (a, account(program_id, vec![1, 2, 3])),
+            (b, account(program_id, vec![1, 3])),
+            (c, account(Pubkey::new_unique(), vec![1, 2, 3])),
+        ]);
+
+        assert_eq!(
+            fetcher.get_account(&a).await.unwrap().unwrap().data,
+            [1, 2, 3]
+        );
+        let accounts = fetcher
+            .get_multiple_accounts(&[b, Pubkey::new_unique(), a])
+            .await
+            .unwrap();
+        assert_eq!(
+            accounts.iter().map(Option::is_some).collect::<Vec<_>>(),
+            [true, false, true]
+        );
+
+        let res = fetcher
+            .get_program_accounts(
+                &program_id,
+                vec![
+                    RpcFilterType::Memcmp(Memcmp::new_raw_bytes(1, vec![2])),
+                    RpcFilterType::DataSize(3),
+                ],
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].0, a);
+        let res = fetcher
+            .get_program_accounts(&program_id, vec![])
+            .await
+            .unwrap();
+        assert_eq!(res.len(), 2);
+    }
+}
@@ -1,21 +1,22 @@
-use solana_program::{pubkey, pubkey::Pubkey};
+use solana_program::pubkey::Pubkey;
 
+pub mod config;
 pub mod derivation;
+pub mod domain_name;
 pub mod error;
 pub mod favourite_domain;
+pub mod fetcher;
 pub mod register;
 mod utils;
 
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
+
 pub mod record;
 
-#[cfg(not(feature = "blocking"))]
 pub mod non_blocking;
 
 #[cfg(feature = "blocking")]
 pub mod blocking;
 
-#[cfg(not(feature = "devnet"))]
-pub const NAME_OFFERS_PROGRAM_ID: Pubkey = pubkey!("85iDfUvr3HJyLM2zcq5BXSiDvUWfw6cSE1FfNBo8Ap29");
-
-#[cfg(feature = "devnet")]
-pub const NAME_OFFERS_PROGRAM_ID: Pubkey = pubkey!("nameaSUMPQLdPzSimWStRKQyuwwiKscgWnZ6FSsT4zn");
+pub const NAME_OFFERS_PROGRAM_ID: Pubkey = config::SnsConfig::DEFAULT.name_offers_program_id;
@@ -0,0 +1,788 @@
+use name_tokenizer::state::NftRecord;
+use solana_program::{instruction::Instruction, program_pack::Pack, pubkey::Pubkey};
+use solana_sdk::signer::Signer;
+use spl_name_service::state::NameRecordHeader;
+use spl_token::state::Mint;
+use std::collections::HashMap;
+
+use crate::{
+    error::SnsError,
+    fetcher::{get_multiple_accounts_chunked, AccountFetcher},
+    non_blocking::resolve::{deserialize_name_registry, find_nft_holder},
+    record::{
+        record_v1::{deserialize_record, sign_sol_record},
+        record_v2::{parse_record_v2, split_record_v2, RecordVerification, ValidationId},
+        value::RecordValue,
+        Record, RecordKey, RecordStatus, RecordVersion, RecordsBatch, ResolvedRecord,
+    },
+};
+
+/// Resolves a record of a domain, whatever its version
+///
+/// The record V2 is used when it was signed by the current owner of the domain, otherwise the
+/// record V1 is used if it exists. A stale record V2 is only returned when there is no readable
+/// record V1, and records which cannot be read are returned with the `Invalid` status. Returns
+/// `None` if the domain or the record does not exist.
+pub async fn get_record(
+    fetcher: &(impl AccountFetcher + ?Sized),
+    domain: &str,
+    record: Record,
+) -> Result<Option<ResolvedRecord>, SnsError> {
+    let config = fetcher.config();
+    let domain_key = config.get_domain_key(domain)?;
+    let v1_key = config.get_record_key(domain, record, RecordVersion::V1)?;
+    let v2_key = config.get_record_key(domain, record, RecordVersion::V2)?;
+    let mut accounts = fetcher
+        .get_multiple_accounts(&[domain_key, v2_key, v1_key])
+        .await?
+        .into_iter();
+    let (Some(registry), v2, v1) = (
+        accounts.next().flatten(),
+        accounts.next().flatten(),
+        accounts.next().flatten(),
+    ) else {
+        return Ok(None);
+    };
+    let (header, _) = deserialize_name_registry(&registry.data)?;
+    let owner = match v2 {
+        Some(_) => get_record_signer(fetcher, &domain_key, &header).await?,
+        None => ValidationId::Solana(header.owner),
+    };
+    Ok(select_record(
+        record,
+        config.get_guardians(record),
+        &owner,
+        v2.as_ref().map(|a| a.data.as_slice()),
+        v1.as_ref().map(|a| (&v1_key, a.data.as_slice())),
+    )
+    .await)
+}
+
+/// Resolves several records of several domains, see `get_record`
+///
+/// All the domain and record accounts are fetched together in chunks of
+/// `MAX_MULTIPLE_ACCOUNTS`, as well as the accounts needed to find the owners of tokenized and
+/// bridged domains with records V2. The result has one entry per domain, in the same order, which
+/// is `None` if the domain is invalid or does not exist. Records that do not exist are left out,
+/// and records which cannot be read have the `Invalid` status.
+pub async fn get_records_batch(
+    fetcher: &(impl AccountFetcher + ?Sized),
+    domains: &[&str],
+    records: &[Record],
+) -> Result<RecordsBatch, SnsError> {
+    let config = fetcher.config();
+    // For each valid domain: the domain key then the (V2, V1) keys of every record
+    let derive_keys = |domain: &str| -> Result<Vec<Pubkey>, SnsError> {
+        let mut keys = vec![config.get_domain_key(domain)?];
+        for record in records {
+            keys.push(config.get_record_key(domain, *record, RecordVersion::V2)?);
+            keys.push(config.get_record_key(domain, *record, RecordVersion::V1)?);
+        }
+        Ok(keys)
+    };
+    let domain_keys = domains
+        .iter()
+        .map(|domain| derive_keys(domain).ok())
+        .collect::<Vec<_>>();
+    let keys = domain_keys
+        .iter()
+        .flatten()
+        .flatten()
+        .copied()
+        .collect::<Vec<_>>();
+    let accounts = get_multiple_accounts_chunked(fetcher, &keys).await?;
+    let mut accounts = accounts.chunks(1 + 2 * records.len());
+
+    let found = domain_keys
+        .iter()
+        .map(|keys| {
+            let keys = keys.as_ref()?;
+            let accounts = accounts.next()?;
+            let (header, _) = deserialize_name_registry(&accounts[0].as_ref()?.data).ok()?;
+            Some((keys, accounts, header))
+        })
+        .collect::<Vec<_>>();
+    // Only the owners of domains with records V2 need to be looked up
+    let with_v2 = found
+        .iter()
+        .flatten()
+        .filter(|(_, accounts, _)| accounts[1..].iter().step_by(2).any(Option::is_some))
+        .map(|(keys, _, header)| (keys[0], header))
+        .collect::<Vec<_>>();
+    let signers = get_record_signers(fetcher, &with_v2).await?;
+    let signers = with_v2
+        .iter()
+        .map(|(key, _)| *key)
+        .zip(signers)
+        .collect::<HashMap<_, _>>();
+
+    let mut res = RecordsBatch::with_capacity(domains.len());
+    for entry in &found {
+        let Some((keys, accounts, header)) = entry else {
+            res.push(None);
+            continue;
+        };
+        let owner = signers
+            .get(&keys[0])
+            .cloned()
+            .unwrap_or(ValidationId::Solana(header.owner));
+        let mut resolved = HashMap::new();
+        for (i, record) in records.iter().enumerate() {
+            let (v2, v1) = (&accounts[1 + 2 * i], &accounts[2 + 2 * i]);
+            let value = select_record(
+                *record,
+                config.get_guardians(*record),
+                &owner,
+                v2.as_ref().map(|a| a.data.as_slice()),
+                v1.as_ref().map(|a| (&keys[2 + 2 * i], a.data.as_slice())),
+            )
+            .await;
+            if let Some(value) = value {
+                resolved.insert(*record, value);
+            }
+        }
+        res.push(Some(resolved));
+    }
+    Ok(res)
+}
+
+/// Reports the validations of the record V2 of a domain against its current owner, to explain
+/// why the record is trusted or not
+///
+/// Returns `None` if the domain or the record V2 does not exist.
+pub async fn get_record_v2_verification(
+    fetcher: &(impl AccountFetcher + ?Sized),
+    domain: &str,
+    record: Record,
+) -> Result<Option<RecordVerification>, SnsError> {
+    let config = fetcher.config();
+    let domain_key = config.get_domain_key(domain)?;
+    let v2_key = config.get_record_key(domain, record, RecordVersion::V2)?;
+    let mut accounts = fetcher
+        .get_multiple_accounts(&[domain_key, v2_key])
+        .await?
+        .into_iter();
+    let (Some(registry), Some(v2)) = (accounts.next().flatten(), accounts.next().flatten()) else {
+        return Ok(None);
+    };
+    let (header, _) = deserialize_name_registry(&registry.data)?;
+    let owner = get_record_signer(fetcher, &domain_key, &header).await?;
+    split_record_v2(&v2.data)?
+        .verification(record, config.get_guardians(record), owner)
+        .map(Some)
+}
+
+/// Fetches the raw content of a record, which can be a custom record
+///
+/// The record V2 takes precedence over the record V1, no verification is performed. Returns
+/// `None` if neither exists.
+pub async fn get_raw_record(
+    fetcher: &(impl AccountFetcher + ?Sized),
+    domain: &str,
+    record: impl Into<RecordKey>,
+) -> Result<Option<(RecordVersion, Vec<u8>)>, SnsError> {
+    let config = fetcher.config();
+    let record = record.into();
+    let v2_key = config.get_record_key(domain, record.clone(), RecordVersion::V2)?;
+    let v1_key = config.get_record_key(domain, record, RecordVersion::V1)?;
+    let mut accounts = fetcher
+        .get_multiple_accounts(&[v2_key, v1_key])
+        .await?
+        .into_iter();
+    if let Some(v2) = accounts.next().flatten() {
+        let content = split_record_v2(&v2.data)?.content.to_vec();
+        return Ok(Some((RecordVersion::V2, content)));
+    }
+    match accounts.next().flatten() {
+        Some(v1) => {
+            let (_, content) = deserialize_name_registry(&v1.data)?;
+            Ok(Some((RecordVersion::V1, content)))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Builds the instructions writing `data` in the record V1 of a domain
+///
+/// The record account is created if it does not exist and reallocated when its size changes,
+/// `data` is serialized with `serialize_record` or `serialize_sol_record`.
+pub async fn set_record_v1(
+    fetcher: &(impl AccountFetcher + ?Sized),
+    domain: &str,
+    record: impl Into<RecordKey>,
+    data: Vec<u8>,
+    owner: &Pubkey,
+    payer: &Pubkey,
+) -> Result<Vec<Instruction>, SnsError> {
+    let config = fetcher.config();
+    let record = record.into();
+    let key = config.get_record_key(domain, record.clone(), RecordVersion::V1)?;
+    match fetcher.get_account(&key).await? {
+        Some(account) => config.update_record_v1_instructions(
+            domain,
+            record,
+            data,
+            account.data.len().saturating_sub(NameRecordHeader::LEN),
+            owner,
+            payer,
+        ),
+        None => config.create_record_v1_instructions(domain, record, data, owner, payer),
+    }
+}
+
+/// Builds the instructions pointing the SOL record V1 of a domain to `content`, signed by the
+/// domain owner `signer`
+pub async fn set_sol_record_v1(
+    fetcher: &(impl AccountFetcher + ?Sized),
+    domain: &str,
+    content: &Pubkey,
+    signer: &(impl Signer + ?Sized),
+    payer: &Pubkey,
+) -> Result<Vec<Instruction>, SnsError> {
+    let key = fetcher
+        .config()
+        .get_record_key(domain, Record::Sol, RecordVersion::V1)?;
+    let data = sign_sol_record(content, &key, signer)?;
+    set_record_v1(
+        fetcher,
+        domain,
+        Record::Sol,
+        data,
+        &signer.try_pubkey()?,
+        payer,
+    )
+    .await
+}
+
+/// The identity records V2 have to be signed by: the NFT holder for tokenized domains, the owner
+/// on the other chain for bridged domains and the registry owner otherwise
+pub(crate) async fn get_record_signer(
+    fetcher: &(impl AccountFetcher + ?Sized),
+    domain_key: &Pubkey,
+    header: &NameRecordHeader,
+) -> Result<ValidationId, SnsError> {
+    let mut signers = get_record_signers(fetcher, &[(*domain_key, header)]).await?;
+    Ok(signers.pop().unwrap_or(ValidationId::Solana(header.owner)))
+}
+
+/// `get_record_signer` for several domains
+///
+/// The `XDomain` accounts of bridged domains and the NFT mints of tokenized domains are fetched
+/// together, then the NFT holders are looked up concurrently. Bridged domains whose `XDomain`
+/// account cannot be read have no owner, so all their records are stale.
+pub(crate) async fn get_record_signers(
+    fetcher: &(impl AccountFetcher + ?Sized),
+    domains: &[(Pubkey, &NameRecordHeader)],
+) -> Result<Vec<ValidationId>, SnsError> {
+    let config = fetcher.config();
+    let keys = domains
+        .iter()
+        .map(|(domain_key, header)| {
+            if header.owner == config.warp_emitter {
+                return config.get_x_domain_key(domain_key);
+            }
+            let (nft_record, _) = NftRecord::find_key(domain_key, &config.name_tokenizer_id);
+            (header.owner == nft_record).then(|| config.get_domain_mint(domain_key))
+        })
+        .collect::<Vec<_>>();
+    let mut accounts =
+        get_multiple_accounts_chunked(fetcher, &keys.iter().flatten().copied().collect::<Vec<_>>())
+            .await?
+            .into_iter();
+    let accounts = keys
+        .iter()
+        .map(|key| key.and_then(|_| accounts.next().flatten()))
+        .collect::<Vec<_>>();
+
+    let signers = domains
+        .iter()
+        .zip(accounts)
+        .map(|((domain_key, header), account)| async move {
+            if header.owner == config.warp_emitter {
+                let data = account.map(|a| a.data);
+                return Ok(config
+                    .get_domain_owner_id(header.owner, data.as_deref())
+                    .unwrap_or(ValidationId::None));
+            }
+            let tokenized =
+                account.is_some_and(|a| Mint::unpack(&a.data).is_ok_and(|m| m.supply == 1));
+            let holder = if tokenized {
+                let mint_key = config.get_domain_mint(domain_key);
+                find_nft_holder(fetcher, &mint_key)
+                    .await?
+                    .map(|(_, owner)| owner)
+            } else {
+                None
+            };
+            Ok(ValidationId::Solana(holder.unwrap_or(header.owner)))
+        });
+    futures::future::try_join_all(signers).await
+}
+
+/// Picks the record to trust between the record V2 and the record V1 accounts of a domain
+///
+/// `owner` is the identity records V2 have to be signed by, see `get_record_signer`, `v2` the
+/// data of the record V2 account and `v1` the key and data of the record V1 account.
+///
+/// A record V2 which cannot be read falls back to the record V1 like a stale one. When neither
+/// can be read, the record is returned with the `Invalid` status and its raw content.
+pub(crate) async fn select_record(
+    record: Record,
+    guardians: &[Pubkey],
+    owner: &ValidationId,
+    v2: Option<&[u8]>,
+    v1: Option<(&Pubkey, &[u8])>,
+) -> Option<ResolvedRecord> {
+    let mut fallback = None;
+    if let Some(data) = v2 {
+        let resolved = match parse_record_v2(record, data).await {
+            Ok(parsed) => {
+                let status = if parsed.verify_staleness(owner).is_err() {
+                    RecordStatus::Stale
+                } else if parsed.verify_roa(guardians).is_err() {
+                    RecordStatus::UnverifiedRoa
+                } else {
+                    RecordStatus::Verified
+                };
+                ResolvedRecord {
+                    record,
+                    version: RecordVersion::V2,
+                    content: parsed.content,
+                    status,
+                }
+            }
+            Err(_) => {
+                let content = split_record_v2(data).map_or(
+                    data.get(NameRecordHeader::LEN..).unwrap_or_default(),
+                    |raw| raw.content,
+                );
+                invalid_record(record, RecordVersion::V2, content)
+            }
+        };
+        if matches!(
+            resolved.status,
+            RecordStatus::Verified | RecordStatus::UnverifiedRoa
+        ) {
+            return Some(resolved);
+        }
+        fallback = Some(resolved);
+    }
+
+    if let Some((key, data)) = v1 {
+        let content = deserialize_name_registry(data)
+            .and_then(|(header, data)| deserialize_record(&data, record, key, &header.owner));
+        match content {
+            Ok(content) => {
+                return Some(ResolvedRecord {
+                    record,
+                    version: RecordVersion::V1,
+                    content,
+                    status: RecordStatus::Unverified,
+                })
+            }
+            Err(_) if fallback.is_none() => {
+                let content = data.get(NameRecordHeader::LEN..).unwrap_or_default();
+                fallback = Some(invalid_record(record, RecordVersion::V1, content));
+            }
+            Err(_) => {}
+        }
+    }
+
+    fallback
+}
+
+fn invalid_record(record: Record, version: RecordVersion, content: &[u8]) -> ResolvedRecord {
+    ResolvedRecord {
+        record,
+        version,
+        content: RecordValue::Raw(content.to_vec()),
+        status: RecordStatus::Invalid,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetcher::{InMemoryFetcher, MAX_MULTIPLE_ACCOUNTS};
+    use crate::record::{
+        record_v1::verify_sol_record,
+        record_v2::{ValidationId, GUARDIAN_ID},
+    };
+    use crate::test_utils::{LedgerBuilder, MockRecordV2};
+    use sns_records::state::validation::Validation;
+    use solana_client::rpc_filter::RpcFilterType;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_get_record() {
+        let owner = Pubkey::new_unique();
+        let previous_owner = Pubkey::new_unique();
+        let url = |content| MockRecordV2::new(Record::Url, content);
+        let fetcher = LedgerBuilder::new()
+            .domain("verified", owner)
+            .record_v2(
+                "verified",
+                Record::Url,
+                url("https://v2.sns.id")
+                    .staleness(&owner)
+                    .roa(Validation::Solana, GUARDIAN_ID.as_ref()),
+            )
+            .record_v1("verified", Record::Url, b"https://v1.sns.id")
+            .domain("unverified-roa", owner)
+            .record_v2(
+                "unverified-roa",
+                Record::Url,
+                url("https://v2.sns.id").staleness(&owner),
+            )
+            .domain("stale", owner)
+            .record_v2(
+                "stale",
+                Record::Url,
+                url("https://v2.sns.id").staleness(&previous_owner),
+            )
+            .domain("stale-with-v1", owner)
+            .record_v2(
+                "stale-with-v1",
+                Record::Url,
+                url("https://v2.sns.id").staleness(&previous_owner),
+            )
+            .record_v1("stale-with-v1", Record::Url, b"https://v1.sns.id\0\0")
+            .domain("invalid-with-v1", owner)
+            .record_v2("invalid-with-v1", Record::A, MockRecordV2::raw(&[10, 0]))
+            .record_v1("invalid-with-v1", Record::A, &[10, 0, 0, 1])
+            .domain("invalid", owner)
+            .record_v2("invalid", Record::A, MockRecordV2::raw(&[10, 0]))
+            .domain("empty", owner)
+            .build();
+
+        let get = |domain| get_record(&fetcher, domain, Record::Url);
+        let check = |res: Option<ResolvedRecord>, version, content: &str, status| {
+            let res = res.unwrap();
+            assert_eq!(res.record, Record::Url);
+            assert_eq!(res.version, version);
+            assert_eq!(res.content.to_string(), content);
+            assert_eq!(res.status, status);
+        };
+        use RecordVersion::*;
+        check(
+            get("verified").await.unwrap(),
+            V2,
+            "https://v2.sns.id",
+            RecordStatus::Verified,
+        );
+        check(
+            get("unverified-roa").await.unwrap(),
+            V2,
+            "https://v2.sns.id",
+            RecordStatus::UnverifiedRoa,
+        );
+        check(
+            get("stale").await.unwrap(),
+            V2,
+            "https://v2.sns.id",
+            RecordStatus::Stale,
+        );
+        check(
+            get("stale-with-v1").await.unwrap(),
+            V1,
+            "https://v1.sns.id",
+            RecordStatus::Unverified,
+        );
+        assert!(get("empty").await.unwrap().is_none());
+        assert!(get("missing").await.unwrap().is_none());
+
+        let get_a = |domain| get_record(&fetcher, domain, Record::A);
+        let res = get_a("invalid-with-v1").await.unwrap().unwrap();
+        assert_eq!((res.version, res.status), (V1, RecordStatus::Unverified));
+        assert_eq!(res.content.to_string(), "10.0.0.1");
+        let res = get_a("invalid").await.unwrap().unwrap();
+        assert_eq!((res.version, res.status), (V2, RecordStatus::Invalid));
+        assert_eq!(res.content, RecordValue::Raw(vec![10, 0]));
+
+        let verification = |domain| get_record_v2_verification(&fetcher, domain, Record::Url);
+        let res = verification("stale").await.unwrap().unwrap();
+        assert_eq!(res.record_owner, ValidationId::Solana(previous_owner));
+        assert_eq!(res.domain_owner, ValidationId::Solana(owner));
+        assert_eq!(res.status(), RecordStatus::Stale);
+        let res = verification("unverified-roa").await.unwrap().unwrap();
+        assert_eq!(res.roa_signer, ValidationId::None);
+        assert_eq!(res.guardians, [GUARDIAN_ID]);
+        assert_eq!(res.status(), RecordStatus::UnverifiedRoa);
+        assert!(verification("empty").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_get_record_tokenized() {
+        let owner = Pubkey::new_unique();
+        let holder = Pubkey::new_unique();
+        let fetcher = LedgerBuilder::new()
+            .domain("tokenized", owner)
+            .record_v2(
+                "tokenized",
+                Record::TXT,
+                MockRecordV2::new(Record::TXT, "hello").staleness(&holder),
+            )
+            .tokenize("tokenized", holder)
+            .build();
+        let res = get_record(&fetcher, "tokenized", Record::TXT)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.status, RecordStatus::Verified);
+    }
+
+    #[tokio::test]
+    async fn test_get_record_bridged() {
+        let owner = Pubkey::new_unique();
+        let config = crate::config::SnsConfig {
+            warp_program_id: Some(Pubkey::new_unique()),
+            ..crate::config::SnsConfig::DEFAULT
+        };
+        let txt = || MockRecordV2::new(Record::TXT, "hello");
+        let fetcher = LedgerBuilder::with_config(config)
+            .domain("signed", owner)
+            .record_v2("signed", Record::TXT, txt().xchain_staleness(30, &[7; 32]))
+            .bridge("signed", 30, [7; 32])
+            .domain("stale", owner)
+            .record_v2("stale", Record::TXT, txt().xchain_staleness(30, &[8; 32]))
+            .bridge("stale", 30, [7; 32])
+            .build();
+
+        let get = |domain| get_record(&fetcher, domain, Record::TXT);
+        let res = get("signed").await.unwrap().unwrap();
+        assert_eq!(res.status, RecordStatus::Verified);
+        let res = get("stale").await.unwrap().unwrap();
+        assert_eq!(res.status, RecordStatus::Stale);
+
+        let res = get_record_v2_verification(&fetcher, "signed", Record::TXT)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            res.domain_owner,
+            ValidationId::XChain {
+                chain: 30,
+                address: [7; 32]
+            }
+        );
+        assert!(!res.is_stale());
+    }
+
+    /// Counts the `get_multiple_accounts` calls
+    struct CountingFetcher(InMemoryFetcher, AtomicUsize);
+
+    #[async_trait::async_trait]
+    impl AccountFetcher for CountingFetcher {
+        fn config(&self) -> &crate::config::SnsConfig {
+            self.0.config()
+        }
+
+        async fn get_account(
+            &self,
+            key: &Pubkey,
+        ) -> Result<Option<solana_sdk::account::Account>, SnsError> {
+            self.0.get_account(key).await
+        }
+
+        async fn get_multiple_accounts(
+            &self,
+            keys: &[Pubkey],
+        ) -> Result<Vec<Option<solana_sdk::account::Account>>, SnsError> {
+            assert!(keys.len() <= MAX_MULTIPLE_ACCOUNTS);
+            self.1.fetch_add(1, Ordering::Relaxed);
+            self.0.get_multiple_accounts(keys).await
+        }
+
+        async fn get_program_accounts(
+            &self,
+            program_id: &Pubkey,
+            filters: Vec<RpcFilterType>,
+        ) -> Result<Vec<(Pubkey, solana_sdk::account::Account)>, SnsError> {
+            self.0.get_program_accounts(program_id, filters).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_records_batch() {
+        let owner = Pubkey::new_unique();
+        let domains = (0..40).map(|i| format!("domain{i}")).collect::<Vec<_>>();
+        let mut ledger = LedgerBuilder::new();
+        for (i, domain) in domains.iter().enumerate() {
+            ledger = ledger.domain(domain, owner);
+            if i % 2 == 0 {
+                ledger = ledger.record_v2(
+                    domain,
+                    Record::TXT,
+                    MockRecordV2::new(Record::TXT, domain).staleness(&owner),
+                );
+            }
+            if i % 3 == 0 {
+                ledger = ledger.record_v1(domain, Record::Url, b"https://sns.id");
+            }
+        }
+        let fetcher = CountingFetcher(ledger.build(), AtomicUsize::new(0));
+
+        let mut names = domains.iter().map(String::as_str).collect::<Vec<_>>();
+        names.extend(["missing", "in..valid"]);
+        let records = [Record::TXT, Record::Url];
+        let res = get_records_batch(&fetcher, &names, &records).await.unwrap();
+        // 41 valid domains * (1 domain + 2 records * 2 versions) accounts
+        assert_eq!(fetcher.1.load(Ordering::Relaxed), 3);
+
+        assert_eq!(res.len(), names.len());
+        assert!(res[domains.len()..].iter().all(Option::is_none));
+        for (i, domain) in domains.iter().enumerate() {
+            let resolved = res[i].as_ref().unwrap();
+            for record in records {
+                assert_eq!(resolved.get(&record), {
+                    get_record(&fetcher.0, domain, record)
+                        .await
+                        .unwrap()
+                        .as_ref()
+                });
+            }
+            assert_eq!(resolved.contains_key(&Record::TXT), i % 2 == 0);
+            assert_eq!(resolved.contains_key(&Record::Url), i % 3 == 0);
+        }
+        let txt = &res[0].as_ref().unwrap()[&Record::TXT];
+        assert_eq!(txt.content.to_string(), "domain0");
+        assert_eq!(txt.status, RecordStatus::Verified);
+    }
+
+    #[tokio::test]
+    async fn test_get_records_batch_owners() {
+        let owner = Pubkey::new_unique();
+        let holder = Pubkey::new_unique();
+        let config = crate::config::SnsConfig {
+            warp_program_id: Some(Pubkey::new_unique()),
+            ..crate::config::SnsConfig::DEFAULT
+        };
+        let txt = || MockRecordV2::new(Record::TXT, "hello");
+        let ledger = LedgerBuilder::with_config(config.clone())
+            .domain("tokenized", owner)
+            .record_v2("tokenized", Record::TXT, txt().staleness(&holder))
+            .tokenize("tokenized", holder)
+            .domain("bridged", owner)
+            .record_v2("bridged", Record::TXT, txt().xchain_staleness(30, &[7; 32]))
+            .bridge("bridged", 30, [7; 32])
+            .domain("unbridged", config.warp_emitter)
+            .record_v2("unbridged", Record::TXT, txt().staleness(&owner))
+            .domain("invalid", owner)
+            .record_v2("invalid", Record::A, MockRecordV2::raw(&[10, 0]))
+            .record_v2("invalid", Record::TXT, txt().staleness(&owner));
+        let fetcher = CountingFetcher(ledger.build(), AtomicUsize::new(0));
+
+        let domains = ["tokenized", "bridged", "unbridged", "invalid"];
+        let res = get_records_batch(&fetcher, &domains, &[Record::TXT, Record::A])
+            .await
+            .unwrap();
+        // The records, then the NFT mint and the XDomain accounts
+        assert_eq!(fetcher.1.load(Ordering::Relaxed), 2);
+
+        let status = |domain: &str, record| {
+            let i = domains.iter().position(|d| *d == domain).unwrap();
+            res[i].as_ref().unwrap()[&record].status
+        };
+        assert_eq!(status("tokenized", Record::TXT), RecordStatus::Verified);
+        assert_eq!(status("bridged", Record::TXT), RecordStatus::Verified);
+        assert_eq!(status("unbridged", Record::TXT), RecordStatus::Stale);
+        assert_eq!(status("invalid", Record::TXT), RecordStatus::Verified);
+        assert_eq!(status("invalid", Record::A), RecordStatus::Invalid);
+    }
+
+    #[tokio::test]
+    async fn test_set_record_v1() {
+        use borsh::BorshDeserialize;
+        use spl_name_service::instruction::NameRegistryInstruction;
+
+        let owner = Pubkey::new_unique();
+        let fetcher = LedgerBuilder::new()
+            .domain("bonfida", owner)
+            .record_v1("bonfida", Record::Url, b"https://sns.id")
+            .build();
+        let set = |record, data: &[u8]| {
+            set_record_v1(&fetcher, "bonfida", record, data.to_vec(), &owner, &owner)
+        };
+        let tags = |ixs: Vec<Instruction>| {
+            ixs.into_iter()
+                .map(|ix| NameRegistryInstruction::try_from_slice(&ix.data).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let ixs = tags(set(Record::Url, b"https://sns.io").await.unwrap());
+        assert!(matches!(
+            ixs.as_slice(),
+            [NameRegistryInstruction::Update { offset: 0, data }] if data == b"https://sns.io"
+        ));
+        let ixs = tags(set(Record::Url, b"https://bonfida.org").await.unwrap());
+        assert!(matches!(
+            ixs.as_slice(),
+            [
+                NameRegistryInstruction::Realloc { space: 19 },
+                NameRegistryInstruction::Update { .. }
+            ]
+        ));
+        let ixs = tags(set(Record::Twitter, b"@bonfida").await.unwrap());
+        let rent = crate::record::record_v1::get_record_v1_rent(8);
+        assert!(matches!(
+            ixs.as_slice(),
+            [
+                NameRegistryInstruction::Create { lamports, space: 8, .. },
+                NameRegistryInstruction::Update { .. }
+            ] if *lamports == rent
+        ));
+
+        let signer = solana_sdk::signature::Keypair::new();
+        let target = Pubkey::new_unique();
+        let ixs = set_sol_record_v1(&fetcher, "bonfida", &target, &signer, &owner)
+            .await
+            .unwrap();
+        let key = crate::record::get_record_v1_key("bonfida", Record::Sol).unwrap();
+        let ixs = tags(ixs);
+        let [NameRegistryInstruction::Create { space: 96, .. }, NameRegistryInstruction::Update { data, .. }] =
+            ixs.as_slice()
+        else {
+            panic!("Unexpected instructions {ixs:?}");
+        };
+        assert_eq!(
+            verify_sol_record(data, &key, &signer.pubkey()).unwrap(),
+            Some(target)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_raw_record() {
+        let owner = Pubkey::new_unique();
+        let custom = RecordKey::new("x-ourapp-config");
+        let fetcher = LedgerBuilder::new()
+            .domain("v1", owner)
+            .record_v1("v1", custom.clone(), b"{\"theme\":\"dark\"}")
+            .domain("v2", owner)
+            .record_v2("v2", custom.clone(), MockRecordV2::raw(&[0, 1, 2]))
+            .record_v1("v2", custom.clone(), b"v1")
+            .domain("known", owner)
+            .record_v1("known", Record::Url, b"https://sns.id")
+            .build();
+
+        assert_eq!(
+            get_raw_record(&fetcher, "v1", custom.clone())
+                .await
+                .unwrap(),
+            Some((RecordVersion::V1, b"{\"theme\":\"dark\"}".to_vec()))
+        );
+        assert_eq!(
+            get_raw_record(&fetcher, "v2", custom.clone())
+                .await
+                .unwrap(),
+            Some((RecordVersion::V2, vec![0, 1, 2]))
+        );
+        assert_eq!(
+            get_raw_record(&fetcher, "known", "url").await.unwrap(),
+            Some((RecordVersion::V1, b"https://sns.id".to_vec()))
+        );
+        assert_eq!(
+            get_raw_record(&fetcher, "known", custom).await.unwrap(),
+            None
+        );
+    }
+}
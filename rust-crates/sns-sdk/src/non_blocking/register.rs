@@ -1,20 +1,18 @@
-use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_program::{message::Message, pubkey::Pubkey, sysvar};
 use solana_sdk::transaction::Transaction;
 use spl_associated_token_account::get_associated_token_address;
 
 use crate::{
-    derivation::{get_domain_key, get_reverse_key, ROOT_DOMAIN_ACCOUNT},
     error::SnsError,
+    fetcher::AccountFetcher,
     register::{
         create::{Accounts, Params},
-        get_register_instruction, PYTH_MAPPING_ACC, PYTH_PRICE_PRODUCT_ACCOUNTS, REFERRERS,
-        REGISTER_PROGRAM_ID, USDC_MINT, VAULT_OWNER,
+        get_register_instruction,
     },
 };
 
 pub async fn register_domain_name(
-    rpc_client: &RpcClient,
+    fetcher: &(impl AccountFetcher + ?Sized),
     name: &str,
     space: u32,
     buyer: &Pubkey,
@@ -22,29 +20,29 @@ pub async fn register_domain_name(
     mint: Option<&Pubkey>,
     referrer_key: Option<&Pubkey>,
 ) -> Result<Transaction, SnsError> {
-    let central_state =
-        Pubkey::find_program_address(&[REGISTER_PROGRAM_ID.as_ref()], &REGISTER_PROGRAM_ID).0;
-    let name_account = get_domain_key(name)?;
-    let reverse_lookup_account = get_reverse_key(name)?;
-    let derived_state =
-        Pubkey::find_program_address(&[name_account.as_ref()], &REGISTER_PROGRAM_ID).0;
+    let config = fetcher.config();
+    let program_id = config.register_program_id;
+    let central_state = Pubkey::find_program_address(&[program_id.as_ref()], &program_id).0;
+    let name_account = config.get_domain_key(name)?;
+    let reverse_lookup_account = config.get_reverse_key(name)?;
+    let derived_state = Pubkey::find_program_address(&[name_account.as_ref()], &program_id).0;
     let referrer_idx = if let Some(referrer) = referrer_key {
-        REFERRERS
-            .iter()
-            .enumerate()
-            .find_map(|(i, k)| if k == referrer { Some(i as u16) } else { None })
+        config.referrers.iter().enumerate().find_map(|(i, k)| {
+            if k == referrer {
+                Some(i as u16)
+            } else {
+                None
+            }
+        })
     } else {
         None
     };
-    let mint = mint.unwrap_or(&USDC_MINT);
+    let mint = mint.unwrap_or(&config.usdc_mint);
     let mut instructions = vec![];
     let referrer_token_account = if referrer_idx.is_some() {
         let referrer_token_account =
             spl_associated_token_account::get_associated_token_address(referrer_key.unwrap(), mint);
-        let account = rpc_client
-            .get_account_with_commitment(&referrer_token_account, rpc_client.commitment())
-            .await?
-            .value;
+        let account = fetcher.get_account(&referrer_token_account).await?;
         if account.is_none() {
             let create_account_instruction =
                 spl_associated_token_account::instruction::create_associated_token_account(
@@ -59,7 +57,8 @@ pub async fn register_domain_name(
     } else {
         None
     };
-    let (pyth_price_account, pyth_product_account) = PYTH_PRICE_PRODUCT_ACCOUNTS
+    let (pyth_price_account, pyth_product_account) = config
+        .pyth_price_product_accounts
         .iter()
         .find_map(|(m, price, product)| {
             if m == mint {
@@ -69,19 +68,19 @@ pub async fn register_domain_name(
             }
         })
         .ok_or(SnsError::UnsupportedMint)?;
-    let vault = get_associated_token_address(&VAULT_OWNER, mint);
+    let vault = get_associated_token_address(&config.vault_owner, mint);
     let instruction = get_register_instruction(
-        REGISTER_PROGRAM_ID,
+        program_id,
         Accounts {
             naming_service_program: &spl_name_service::ID,
-            root_domain: &ROOT_DOMAIN_ACCOUNT,
+            root_domain: &config.root_domain_account,
             name: &name_account,
             reverse_lookup: &reverse_lookup_account,
             system_program: &Pubkey::default(),
             central_state: &central_state,
             buyer,
             buyer_token_source: buyer_token_account,
-            pyth_mapping_acc: &PYTH_MAPPING_ACC,
+            pyth_mapping_acc: &config.pyth_mapping_account,
             pyth_product_acc: pyth_product_account,
             pyth_price_acc: pyth_price_account,
             vault: &vault,
@@ -105,9 +104,10 @@ pub async fn register_domain_name(
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::register::FIDA_MINT;
+    use crate::register::{FIDA_MINT, REFERRERS, VAULT_OWNER};
     use crate::utils::test::generate_random_string;
     use dotenv::dotenv;
+    use solana_client::nonblocking::rpc_client::RpcClient;
 
     #[tokio::test]
     async fn test_registration() {
@@ -1,71 +1,67 @@
 use {
     borsh::BorshDeserialize,
     name_tokenizer::state::NftRecord,
-    solana_account_decoder::UiAccountEncoding,
+    sns_warp_common::state::x_domain::XDomain,
     solana_client::{
-        client_error::{ClientError, ClientErrorKind},
-        nonblocking::rpc_client::RpcClient,
-        rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+        client_error::ClientErrorKind,
         rpc_filter::{Memcmp, RpcFilterType},
         rpc_request::RpcError::RpcRequestError,
     },
     solana_program::{program_pack::Pack, pubkey::Pubkey},
-    spl_name_service::state::{get_seeds_and_key, NameRecordHeader},
+    spl_name_service::state::NameRecordHeader,
     spl_token::state::Account,
     spl_token::state::Mint,
+    std::collections::{HashMap, HashSet},
 };
 
 use crate::{
-    derivation::{
-        get_domain_key, get_domain_mint, get_hashed_name, NAME_TOKENIZER_ID, REVERSE_LOOKUP_CLASS,
-        ROOT_DOMAIN_ACCOUNT,
-    },
     error::SnsError,
-    favourite_domain::{derive_favourite_domain_key, FavouriteDomain},
-    record::{get_record_key, record_v1::check_sol_record, Record},
+    favourite_domain::FavouriteDomain,
+    fetcher::{get_multiple_accounts_chunked, AccountFetcher},
+    record::{
+        record_v1::verify_sol_record,
+        record_v2::{parse_record_v2, ValidationId},
+        value::RecordValue,
+        Record, RecordVersion,
+    },
 };
 
+/// Resolves the owner of a domain: the NFT holder of tokenized domains, the content of a SOL
+/// record V1 signed by the owner or the owner of the name registry
+///
+/// Bridged domains resolve to the warp emitter, see `resolve_xchain_owner` for their owner on the
+/// other chain and `resolve_owner_detailed` for the details.
 pub async fn resolve_owner(
-    rpc_client: &RpcClient,
+    fetcher: &(impl AccountFetcher + ?Sized),
     domain: &str,
 ) -> Result<Option<Pubkey>, SnsError> {
-    let key = get_domain_key(domain)?;
+    let config = fetcher.config();
+    let key = config.get_domain_key(domain)?;
 
-    let header = match resolve_name_registry(rpc_client, &key).await? {
+    let header = match resolve_name_registry(fetcher, &key).await? {
         Some((h, _)) => h,
         _ => return Ok(None),
     };
 
-    let nft_owner = resolve_nft_owner(rpc_client, &key).await?;
+    let nft_owner = resolve_nft_owner(fetcher, &key).await?;
 
     if let Some(nft_owner) = nft_owner {
         return Ok(Some(nft_owner));
     }
 
-    let sol_record_key = get_record_key(domain, Record::Sol, crate::record::RecordVersion::V1)?;
-    match resolve_name_registry(rpc_client, &sol_record_key).await {
+    let sol_record_key =
+        config.get_record_key(domain, Record::Sol, crate::record::RecordVersion::V1)?;
+    match resolve_name_registry(fetcher, &sol_record_key).await {
         Ok(Some((_, data))) => {
-            let data = &data[..96];
-            let record = [&data[..32], &sol_record_key.to_bytes()].concat();
-            let sig = &data[32..];
-            let encoded = hex::encode(record);
-            if check_sol_record(encoded.as_bytes(), sig, header.owner)? {
-                let owner = Pubkey::new_from_array(
-                    data[0..32]
-                        .try_into()
-                        .map_err(|_| SnsError::InvalidPubkey)?,
-                );
+            if let Ok(Some(owner)) = verify_sol_record(&data, &sol_record_key, &header.owner) {
                 return Ok(Some(owner));
             }
         }
-        Err(SnsError::SolanaClient(ClientError {
-            request: None,
-            kind: ClientErrorKind::RpcError(RpcRequestError(err)),
-        })) => {
-            return Err(SnsError::SolanaClient(ClientError {
-                request: None,
-                kind: ClientErrorKind::RpcError(RpcRequestError(err)),
-            }))
+        Err(SnsError::SolanaClient(err))
+            if err.request.is_none()
+                && matches!(err.kind, ClientErrorKind::RpcError(RpcRequestError(_))) =>
+        {
+            return Err(SnsError::SolanaClient(err))
         }
         _ => {}
     }
@@ -73,13 +69,220 @@ pub async fn resolve_owner(
     Ok(Some(header.owner))
 }
 
+/// How the owner of a domain was determined, see `resolve_owner_detailed`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResolvedOwner {
+    /// The owner of the name registry
+    Registry(Pubkey),
+    /// The holder of the NFT of a tokenized domain
+    Tokenized {
+        owner: Pubkey,
+        mint: Pubkey,
+        token_account: Pubkey,
+    },
+    /// The content of a SOL record V1 signed by the owner of the name registry
+    SolRecordV1(Pubkey),
+    /// The content of a SOL record V2 signed by the owner of the name registry, `verified` when
+    /// the content also validated its right of association
+    SolRecordV2 {
+        owner: Pubkey,
+        verified: bool,
+        registry_owner: Pubkey,
+    },
+    /// The owner of a domain bridged to another chain, the address is in the Wormhole format
+    CrossChain { chain: u16, address: [u8; 32] },
+}
+
+impl ResolvedOwner {
+    /// The Solana key the domain resolves to, `None` for domains owned on another chain
+    ///
+    /// The content of a SOL record V2 is only used once its right of association is verified,
+    /// the owner of the name registry is returned otherwise.
+    pub fn pubkey(&self) -> Option<Pubkey> {
+        match self {
+            Self::Registry(owner)
+            | Self::Tokenized { owner, .. }
+            | Self::SolRecordV1(owner)
+            | Self::SolRecordV2 {
+                owner,
+                verified: true,
+                ..
+            }
+            | Self::SolRecordV2 {
+                registry_owner: owner,
+                verified: false,
+                ..
+            } => Some(*owner),
+            Self::CrossChain { .. } => None,
+        }
+    }
+}
+
+/// Resolves the owner of a domain and reports how it was determined
+///
+/// In order: the NFT holder of tokenized domains, the owner on the other chain of bridged
+/// domains, a verified SOL record V2, a SOL record V1, an unverified SOL record V2 and finally
+/// the owner of the name registry. SOL records are only used when signed by the registry owner,
+/// records which cannot be read are skipped. Returns `None` if the domain does not exist.
+///
+/// Resolving bridged domains requires `SnsConfig::warp_program_id`, which the presets do not set
+/// yet: they return `SnsError::WarpProgramNotSet` instead of the warp emitter.
+pub async fn resolve_owner_detailed(
+    fetcher: &(impl AccountFetcher + ?Sized),
+    domain: &str,
+) -> Result<Option<ResolvedOwner>, SnsError> {
+    let config = fetcher.config();
+    let domain_key = config.get_domain_key(domain)?;
+    let v1_key = config.get_record_key(domain, Record::Sol, RecordVersion::V1)?;
+    let v2_key = config.get_record_key(domain, Record::Sol, RecordVersion::V2)?;
+    let mut accounts = fetcher
+        .get_multiple_accounts(&[domain_key, v2_key, v1_key])
+        .await?
+        .into_iter();
+    let (Some(registry), v2, v1) = (
+        accounts.next().flatten(),
+        accounts.next().flatten(),
+        accounts.next().flatten(),
+    ) else {
+        return Ok(None);
+    };
+    let (header, _) = deserialize_name_registry(&registry.data)?;
+
+    let (nft_record, _) = NftRecord::find_key(&domain_key, &config.name_tokenizer_id);
+    if header.owner == nft_record {
+        if let Some((token_account, owner)) = resolve_nft_holder(fetcher, &domain_key).await? {
+            return Ok(Some(ResolvedOwner::Tokenized {
+                owner,
+                mint: config.get_domain_mint(&domain_key),
+                token_account,
+            }));
+        }
+    }
+
+    if header.owner == config.warp_emitter {
+        if config.warp_program_id.is_none() {
+            return Err(SnsError::WarpProgramNotSet);
+        }
+        let data = get_x_domain_data(fetcher, &domain_key).await?;
+        if let Some(Ok(x_domain)) = data.map(|data| XDomain::try_from_slice(&data)) {
+            return Ok(Some(ResolvedOwner::CrossChain {
+                chain: x_domain.owner_chain,
+                address: x_domain.owner_address,
+            }));
+        }
+    }
+
+    let parsed = match &v2 {
+        Some(v2) => parse_record_v2(Record::Sol, &v2.data).await.ok(),
+        None => None,
+    };
+    let mut unverified = None;
+    if let Some(parsed) = parsed {
+        let signed = parsed
+            .verify_staleness(&ValidationId::Solana(header.owner))
+            .is_ok();
+        if let (true, RecordValue::Pubkey(owner)) = (signed, &parsed.content) {
+            let verified = parsed.verify_roa(config.get_guardians(Record::Sol)).is_ok();
+            let resolved = ResolvedOwner::SolRecordV2 {
+                owner: *owner,
+                verified,
+                registry_owner: header.owner,
+            };
+            if verified {
+                return Ok(Some(resolved));
+            }
+            unverified = Some(resolved);
+        }
+    }
+
+    if let Some(Ok((_, data))) = v1.map(|v1| deserialize_name_registry(&v1.data)) {
+        if let Ok(Some(owner)) = verify_sol_record(&data, &v1_key, &header.owner) {
+            return Ok(Some(ResolvedOwner::SolRecordV1(owner)));
+        }
+    }
+
+    Ok(Some(
+        unverified.unwrap_or(ResolvedOwner::Registry(header.owner)),
+    ))
+}
+
+/// The owner of a domain bridged to another chain
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct XChainOwner {
+    /// The Wormhole chain ID
+    pub chain: u16,
+    /// The address in the Wormhole format, i.e left-padded with zeros to 32 bytes
+    pub address: [u8; 32],
+}
+
+impl XChainOwner {
+    /// The address as an EVM address, `None` when it does not fit in 20 bytes. It can be
+    /// displayed with `RecordValue::EvmAddress`.
+    pub fn evm_address(&self) -> Option<[u8; 20]> {
+        let (padding, address) = self.address.split_at(12);
+        padding
+            .iter()
+            .all(|b| *b == 0)
+            .then(|| address.try_into().unwrap())
+    }
+}
+
+/// Resolves the owner on the other chain of a domain bridged by the warp program
+///
+/// Returns `None` if the domain does not exist or is not bridged, and an error if its `XDomain`
+/// account cannot be found or if `SnsConfig::warp_program_id` is not set.
+pub async fn resolve_xchain_owner(
+    fetcher: &(impl AccountFetcher + ?Sized),
+    domain: &str,
+) -> Result<Option<XChainOwner>, SnsError> {
+    let domain_key = fetcher.config().get_domain_key(domain)?;
+    let Some((header, _)) = resolve_name_registry(fetcher, &domain_key).await? else {
+        return Ok(None);
+    };
+    if header.owner != fetcher.config().warp_emitter {
+        return Ok(None);
+    }
+    resolve_x_domain(fetcher, &domain_key).await.map(Some)
+}
+
+async fn resolve_x_domain(
+    fetcher: &(impl AccountFetcher + ?Sized),
+    domain_key: &Pubkey,
+) -> Result<XChainOwner, SnsError> {
+    if fetcher.config().warp_program_id.is_none() {
+        return Err(SnsError::WarpProgramNotSet);
+    }
+    let data = get_x_domain_data(fetcher, domain_key)
+        .await?
+        .ok_or(SnsError::XDomainNotFound)?;
+    let x_domain = XDomain::try_from_slice(&data)?;
+    Ok(XChainOwner {
+        chain: x_domain.owner_chain,
+        address: x_domain.owner_address,
+    })
+}
+
+/// Fetches the data of the `XDomain` account of a domain, `None` when it does not exist or when
+/// `SnsConfig::warp_program_id` is not set
+pub(crate) async fn get_x_domain_data(
+    fetcher: &(impl AccountFetcher + ?Sized),
+    domain_key: &Pubkey,
+) -> Result<Option<Vec<u8>>, SnsError> {
+    let Some(key) = fetcher.config().get_x_domain_key(domain_key) else {
+        return Ok(None);
+    };
+    Ok(fetcher.get_account(&key).await?.map(|account| account.data))
+}
+
 pub async fn resolve_record(
-    rpc_client: &RpcClient,
+    fetcher: &(impl AccountFetcher + ?Sized),
     domain: &str,
     record: Record,
 ) -> Result<Option<(NameRecordHeader, Vec<u8>)>, SnsError> {
-    let key = get_record_key(domain, record, crate::record::RecordVersion::V1)?;
-    let res = resolve_name_registry(rpc_client, &key).await?;
+    let key = fetcher
+        .config()
+        .get_record_key(domain, record, crate::record::RecordVersion::V1)?;
+    let res = resolve_name_registry(fetcher, &key).await?;
     if let Some(res) = res {
         Ok(Some(res))
     } else {
@@ -88,26 +291,31 @@ pub async fn resolve_record(
 }
 
 pub fn deserialize_name_registry(data: &[u8]) -> Result<(NameRecordHeader, Vec<u8>), SnsError> {
+    if data.len() < NameRecordHeader::LEN {
+        return Err(SnsError::InvalidRecordData);
+    }
     let header = NameRecordHeader::unpack_unchecked(&data[0..NameRecordHeader::LEN])?;
     let data = data[NameRecordHeader::LEN..].to_vec();
     Ok((header, data))
 }
 
 pub fn deserialize_reverse(data: &[u8]) -> Result<String, SnsError> {
-    let len = u32::from_le_bytes(data[0..4].try_into().unwrap());
-    let reverse =
-        String::from_utf8(data[4..4 + len as usize].to_vec()).or(Err(SnsError::InvalidReverse))?;
-    Ok(reverse)
+    let len = data
+        .get(0..4)
+        .ok_or(SnsError::InvalidReverse)?
+        .try_into()
+        .map(u32::from_le_bytes)?;
+    let reverse = data
+        .get(4..4 + len as usize)
+        .ok_or(SnsError::InvalidReverse)?;
+    String::from_utf8(reverse.to_vec()).or(Err(SnsError::InvalidReverse))
 }
 
 pub async fn resolve_name_registry(
-    rpc_client: &RpcClient,
+    fetcher: &(impl AccountFetcher + ?Sized),
     key: &Pubkey,
 ) -> Result<Option<(NameRecordHeader, Vec<u8>)>, SnsError> {
-    let acc = rpc_client
-        .get_account_with_commitment(key, rpc_client.commitment())
-        .await?
-        .value;
+    let acc = fetcher.get_account(key).await?;
     if let Some(acc) = acc {
         Ok(Some(deserialize_name_registry(&acc.data)?))
     } else {
@@ -116,12 +324,12 @@ pub async fn resolve_name_registry(
 }
 
 pub async fn resolve_name_registry_batch(
-    rpc_client: &RpcClient,
+    fetcher: &(impl AccountFetcher + ?Sized),
     keys: &[Pubkey],
 ) -> Result<Vec<Option<(NameRecordHeader, Vec<u8>)>>, SnsError> {
     let mut res = vec![];
     for k in keys.chunks(100) {
-        let accs = rpc_client.get_multiple_accounts(k).await?;
+        let accs = fetcher.get_multiple_accounts(k).await?;
         for acc in accs {
             if let Some(acc) = acc {
                 let des = deserialize_name_registry(&acc.data)?;
@@ -134,135 +342,164 @@ pub async fn resolve_name_registry_batch(
     Ok(res)
 }
 
+/// Resolves the domain name of a name account, e.g `dex.bonfida` for a subdomain
 pub async fn resolve_reverse(
-    rpc_client: &RpcClient,
+    fetcher: &(impl AccountFetcher + ?Sized),
     key: &Pubkey,
 ) -> Result<Option<String>, SnsError> {
-    let hashed = get_hashed_name(&key.to_string());
-    let (key, _) = get_seeds_and_key(
-        &spl_name_service::ID,
-        hashed,
-        Some(&REVERSE_LOOKUP_CLASS),
-        None,
-    );
-    if let Some((_, data)) = resolve_name_registry(rpc_client, &key).await? {
-        Ok(Some(deserialize_reverse(&data)?))
-    } else {
-        Ok(None)
-    }
+    Ok(resolve_reverse_batch(fetcher, &[*key])
+        .await?
+        .pop()
+        .flatten())
 }
 
+/// Resolves the domain names of a list of name accounts
+///
+/// The parents of subdomains are discovered from the name account headers, which requires one
+/// round of `get_multiple_accounts` calls per level of depth, plus one for the reverse accounts.
 pub async fn resolve_reverse_batch(
-    rpc_client: &RpcClient,
+    fetcher: &(impl AccountFetcher + ?Sized),
     keys: &[Pubkey],
 ) -> Result<Vec<Option<String>>, SnsError> {
-    let mut res = vec![];
+    let root = fetcher.config().root_domain_account;
+    // Name account -> parent name account, for all the accounts that exist
+    let mut parents: HashMap<Pubkey, Pubkey> = HashMap::new();
+    let mut visited: HashSet<Pubkey> = HashSet::new();
+    let mut pending = keys
+        .iter()
+        .filter(|k| visited.insert(**k))
+        .cloned()
+        .collect::<Vec<_>>();
 
-    let reverse_keys = keys
+    while !pending.is_empty() {
+        let accounts = get_multiple_accounts_chunked(fetcher, &pending).await?;
+        let mut next = vec![];
+        for (key, account) in pending.into_iter().zip(accounts) {
+            // Accounts which are not name registries have no name
+            let Some((header, _)) = account.as_ref().and_then(parse_name_registry) else {
+                continue;
+            };
+            let parent = header.parent_name;
+            if parent != root && parent != Pubkey::default() && visited.insert(parent) {
+                next.push(parent);
+            }
+            parents.insert(key, parent);
+        }
+        pending = next;
+    }
+
+    let name_keys = parents.keys().cloned().collect::<Vec<_>>();
+    let reverse_keys = name_keys
         .iter()
         .map(|k| {
-            let hashed = get_hashed_name(&k.to_string());
-            let (key, _) = get_seeds_and_key(
-                &spl_name_service::ID,
-                hashed,
-                Some(&REVERSE_LOOKUP_CLASS),
-                None,
-            );
-            key
+            let parent = parents.get(k).filter(|p| **p != root);
+            fetcher.config().derive_reverse(k, parent)
         })
         .collect::<Vec<_>>();
 
-    let reverses = resolve_name_registry_batch(rpc_client, &reverse_keys).await?;
-    for r in reverses {
-        if let Some((_, data)) = r {
-            let des = deserialize_reverse(&data)?;
-            res.push(Some(des))
-        } else {
-            res.push(None)
+    let mut labels: HashMap<Pubkey, String> = HashMap::new();
+    let reverses = get_multiple_accounts_chunked(fetcher, &reverse_keys).await?;
+    for (key, reverse) in name_keys.into_iter().zip(reverses) {
+        let label = reverse
+            .as_ref()
+            .and_then(parse_name_registry)
+            .and_then(|(_, data)| deserialize_reverse(&data).ok());
+        if let Some(label) = label {
+            labels.insert(key, label.trim_start_matches('\0').to_owned());
         }
     }
 
+    let res = keys
+        .iter()
+        .map(|k| {
+            let mut name = labels.get(k)?.clone();
+            let mut current = *k;
+            // Bounded by the number of accounts visited in case of a cycle
+            for _ in 0..parents.len() {
+                match parents.get(&current) {
+                    Some(parent) if *parent != root => {
+                        name = format!("{name}.{}", labels.get(parent)?);
+                        current = *parent;
+                    }
+                    _ => return Some(name),
+                }
+            }
+            None
+        })
+        .collect();
+
     Ok(res)
 }
 
+/// Deserializes an account of the name service, `None` if it is not a name registry
+fn parse_name_registry(
+    account: &solana_sdk::account::Account,
+) -> Option<(NameRecordHeader, Vec<u8>)> {
+    if account.owner != spl_name_service::ID {
+        return None;
+    }
+    deserialize_name_registry(&account.data).ok()
+}
+
 pub async fn get_domains_owner(
-    rpc_client: &RpcClient,
+    fetcher: &(impl AccountFetcher + ?Sized),
     owner: Pubkey,
 ) -> Result<Vec<Pubkey>, SnsError> {
-    let config = RpcProgramAccountsConfig {
-        filters: Some(vec![
-            RpcFilterType::Memcmp(Memcmp::new_raw_bytes(32, owner.to_bytes().to_vec())),
-            RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
-                0,
-                ROOT_DOMAIN_ACCOUNT.to_bytes().to_vec(),
-            )),
-        ]),
-        with_context: None,
-        account_config: RpcAccountInfoConfig {
-            encoding: Some(UiAccountEncoding::Base64),
-            ..Default::default()
-        },
-    };
-    let res = rpc_client
-        .get_program_accounts_with_config(&spl_name_service::ID, config.clone())
+    let filters = vec![
+        RpcFilterType::Memcmp(Memcmp::new_raw_bytes(32, owner.to_bytes().to_vec())),
+        RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+            0,
+            fetcher.config().root_domain_account.to_bytes().to_vec(),
+        )),
+    ];
+    let res = fetcher
+        .get_program_accounts(&spl_name_service::ID, filters)
         .await?;
     let keys = res.into_iter().map(|x| x.0).collect::<Vec<_>>();
     Ok(keys)
 }
 
 pub async fn get_record_from_mint(
-    rpc_client: &RpcClient,
+    fetcher: &(impl AccountFetcher + ?Sized),
     mint: &Pubkey,
 ) -> Result<Vec<(Pubkey, solana_sdk::account::Account)>, SnsError> {
-    let config = RpcProgramAccountsConfig {
-        filters: Some(vec![
-            RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
-                0,
-                vec![name_tokenizer::state::Tag::ActiveRecord as u8],
-            )),
-            RpcFilterType::Memcmp(Memcmp::new_raw_bytes(66, mint.to_bytes().to_vec())),
-        ]),
-        with_context: None,
-        account_config: RpcAccountInfoConfig {
-            encoding: Some(UiAccountEncoding::Base64),
-            ..Default::default()
-        },
-    };
+    let filters = vec![
+        RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+            0,
+            vec![name_tokenizer::state::Tag::ActiveRecord as u8],
+        )),
+        RpcFilterType::Memcmp(Memcmp::new_raw_bytes(66, mint.to_bytes().to_vec())),
+    ];
 
-    let res = rpc_client
-        .get_program_accounts_with_config(&NAME_TOKENIZER_ID, config.clone())
+    let res = fetcher
+        .get_program_accounts(&fetcher.config().name_tokenizer_id, filters)
         .await?;
 
     Ok(res)
 }
 
 pub async fn get_nft_records(
-    rpc_client: &RpcClient,
+    fetcher: &(impl AccountFetcher + ?Sized),
     owner: &Pubkey,
 ) -> Result<Vec<NftRecord>, SnsError> {
-    let config = RpcProgramAccountsConfig {
-        filters: Some(vec![
-            RpcFilterType::Memcmp(Memcmp::new_raw_bytes(32, owner.to_bytes().to_vec())),
-            RpcFilterType::Memcmp(Memcmp::new_raw_bytes(64, 1u64.to_le_bytes().to_vec())),
-            RpcFilterType::DataSize(165),
-        ]),
-        with_context: None,
-        account_config: RpcAccountInfoConfig {
-            encoding: Some(UiAccountEncoding::Base64),
-            ..Default::default()
-        },
-    };
-    let res = rpc_client
-        .get_program_accounts_with_config(&spl_token::ID, config.clone())
+    let filters = vec![
+        RpcFilterType::Memcmp(Memcmp::new_raw_bytes(32, owner.to_bytes().to_vec())),
+        RpcFilterType::Memcmp(Memcmp::new_raw_bytes(64, 1u64.to_le_bytes().to_vec())),
+        RpcFilterType::DataSize(165),
+    ];
+    let res = fetcher
+        .get_program_accounts(&spl_token::ID, filters)
         .await?
         .into_iter()
         .map(|(_, acc)| Account::unpack(&acc.data))
-        .filter(Result::is_ok)
-        .map(Result::unwrap)
+        .filter_map(Result::ok)
         .collect::<Vec<_>>();
 
-    async fn closure(rpc_client: &RpcClient, acc: &Account) -> Result<NftRecord, SnsError> {
-        let record = get_record_from_mint(rpc_client, &acc.mint).await?;
+    async fn closure(
+        fetcher: &(impl AccountFetcher + ?Sized),
+        acc: &Account,
+    ) -> Result<NftRecord, SnsError> {
+        let record = get_record_from_mint(fetcher, &acc.mint).await?;
         if let Some((_, acc)) = record.first() {
             let des = NftRecord::deserialize(&mut acc.data.as_slice())?;
             return Ok(des);
@@ -270,29 +507,28 @@ pub async fn get_nft_records(
         Err(SnsError::NftRecordDoesNotExist)
     }
 
-    let futures = res.iter().map(|acc| closure(rpc_client, acc));
+    let futures = res.iter().map(|acc| closure(fetcher, acc));
 
     let records = futures::future::join_all(futures)
         .await
         .into_iter()
-        .filter(Result::is_ok)
-        .map(Result::unwrap)
+        .filter_map(Result::ok)
         .collect::<Vec<_>>();
 
     Ok(records)
 }
 
 pub async fn get_tokenized_domains(
-    rpc_client: &RpcClient,
+    fetcher: &(impl AccountFetcher + ?Sized),
     owner: &Pubkey,
 ) -> Result<Vec<(String, Pubkey)>, SnsError> {
-    let pubkeys = get_nft_records(rpc_client, owner)
+    let pubkeys = get_nft_records(fetcher, owner)
         .await?
         .into_iter()
         .map(|r| r.name_account)
         .collect::<Vec<_>>();
 
-    let reverses = resolve_reverse_batch(rpc_client, &pubkeys).await?;
+    let reverses = resolve_reverse_batch(fetcher, &pubkeys).await?;
 
     let mut results = vec![];
 
@@ -306,25 +542,18 @@ pub async fn get_tokenized_domains(
 }
 
 pub async fn get_subdomains(
-    rpc_client: &RpcClient,
+    fetcher: &(impl AccountFetcher + ?Sized),
     parent: &Pubkey,
 ) -> Result<Vec<String>, SnsError> {
-    let config = RpcProgramAccountsConfig {
-        filters: Some(vec![
-            RpcFilterType::Memcmp(Memcmp::new_raw_bytes(0, parent.to_bytes().to_vec())),
-            RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
-                64,
-                REVERSE_LOOKUP_CLASS.to_bytes().to_vec(),
-            )),
-        ]),
-        with_context: None,
-        account_config: RpcAccountInfoConfig {
-            encoding: Some(UiAccountEncoding::Base64),
-            ..Default::default()
-        },
-    };
-    let res = rpc_client
-        .get_program_accounts_with_config(&spl_name_service::ID, config.clone())
+    let filters = vec![
+        RpcFilterType::Memcmp(Memcmp::new_raw_bytes(0, parent.to_bytes().to_vec())),
+        RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+            64,
+            fetcher.config().reverse_lookup_class.to_bytes().to_vec(),
+        )),
+    ];
+    let res = fetcher
+        .get_program_accounts(&spl_name_service::ID, filters)
         .await?;
 
     let res = res
@@ -343,11 +572,21 @@ pub async fn get_subdomains(
 }
 
 pub async fn resolve_nft_owner(
-    rpc_client: &RpcClient,
+    fetcher: &(impl AccountFetcher + ?Sized),
     domain_key: &Pubkey,
 ) -> Result<Option<Pubkey>, SnsError> {
-    let mint_key = get_domain_mint(domain_key);
-    let acc = rpc_client.get_multiple_accounts(&[mint_key]).await?;
+    Ok(resolve_nft_holder(fetcher, domain_key)
+        .await?
+        .map(|(_, owner)| owner))
+}
+
+/// Returns the token account holding the NFT of a tokenized domain and its owner
+async fn resolve_nft_holder(
+    fetcher: &(impl AccountFetcher + ?Sized),
+    domain_key: &Pubkey,
+) -> Result<Option<(Pubkey, Pubkey)>, SnsError> {
+    let mint_key = fetcher.config().get_domain_mint(domain_key);
+    let acc = fetcher.get_multiple_accounts(&[mint_key]).await?;
     let acc = acc.first().ok_or(SnsError::InvalidDomain)?;
     if acc.is_none() {
         return Ok(None);
@@ -356,41 +595,39 @@ pub async fn resolve_nft_owner(
     if mint.supply != 1 {
         return Ok(None);
     }
+    find_nft_holder(fetcher, &mint_key).await
+}
 
-    let config = RpcProgramAccountsConfig {
-        filters: Some(vec![
-            RpcFilterType::Memcmp(Memcmp::new_raw_bytes(0, mint_key.to_bytes().to_vec())),
-            RpcFilterType::Memcmp(Memcmp::new_raw_bytes(64, vec![1])),
-            RpcFilterType::DataSize(165),
-        ]),
-        with_context: None,
-        account_config: RpcAccountInfoConfig {
-            encoding: Some(UiAccountEncoding::Base64),
-            ..Default::default()
-        },
-    };
-    let res = rpc_client
-        .get_program_accounts_with_config(&spl_token::ID, config.clone())
+/// Returns the token account holding the NFT `mint_key` and its owner
+pub(crate) async fn find_nft_holder(
+    fetcher: &(impl AccountFetcher + ?Sized),
+    mint_key: &Pubkey,
+) -> Result<Option<(Pubkey, Pubkey)>, SnsError> {
+    let filters = vec![
+        RpcFilterType::Memcmp(Memcmp::new_raw_bytes(0, mint_key.to_bytes().to_vec())),
+        RpcFilterType::Memcmp(Memcmp::new_raw_bytes(64, vec![1])),
+        RpcFilterType::DataSize(165),
+    ];
+    let res = fetcher
+        .get_program_accounts(&spl_token::ID, filters)
         .await?;
 
-    if let Some((_, acc)) = res.first() {
-        return Ok(Some(
+    if let Some((key, acc)) = res.first() {
+        return Ok(Some((
+            *key,
             spl_token::state::Account::unpack_unchecked(&acc.data)?.owner,
-        ));
+        )));
     }
 
     Ok(None)
 }
 
 pub async fn get_favourite_domain(
-    rpc_client: &RpcClient,
+    fetcher: &(impl AccountFetcher + ?Sized),
     owner: &Pubkey,
 ) -> Result<Option<Pubkey>, SnsError> {
-    let favourite_domain_state_key = derive_favourite_domain_key(owner);
-    let account = rpc_client
-        .get_account_with_commitment(&favourite_domain_state_key, rpc_client.commitment())
-        .await?
-        .value;
+    let favourite_domain_state_key = fetcher.config().derive_favourite_domain_key(owner);
+    let account = fetcher.get_account(&favourite_domain_state_key).await?;
     if let Some(a) = account {
         let parsed = FavouriteDomain::parse(&a.data)?;
         Ok(Some(parsed.name_account))
@@ -405,8 +642,10 @@ mod tests {
     use crate::derivation::get_domain_key;
     use crate::record::record_v1::deserialize_record;
     use crate::record::Record;
+    use crate::test_utils::LedgerBuilder;
     use crate::utils::test::generate_random_string;
     use dotenv::dotenv;
+    use solana_client::nonblocking::rpc_client::RpcClient;
     use solana_program::pubkey;
     use solana_sdk::signature::Keypair;
     use solana_sdk::signer::Signer;
@@ -421,6 +660,10 @@ mod tests {
 
         let reverse = resolve_reverse(&client, &Keypair::new().pubkey()).await;
         assert!(reverse.unwrap().is_none());
+
+        let key = get_domain_key("dex.bonfida").unwrap();
+        let reverse = resolve_reverse(&client, &key).await.unwrap();
+        assert_eq!(reverse.unwrap(), "dex.bonfida");
     }
 
     #[tokio::test]
@@ -475,13 +718,20 @@ mod tests {
             &[
                 pubkey!("Crf8hzfthWGbGbLTVCiqRqV5MVnbpHB1L9KQMd6gsinb"),
                 pubkey!("Crf8hzfthWGbGbLTVCiqRqV5MVnbpHB1L9KQMd6gsinb"),
+                get_domain_key("dex.bonfida").unwrap(),
+                Keypair::new().pubkey(),
             ],
         )
         .await
         .unwrap();
         assert_eq!(
             reverses,
-            vec![Some("bonfida".to_string()), Some("bonfida".to_string())]
+            vec![
+                Some("bonfida".to_string()),
+                Some("bonfida".to_string()),
+                Some("dex.bonfida".to_string()),
+                None
+            ]
         )
     }
 
@@ -494,7 +744,14 @@ mod tests {
             .await
             .unwrap();
         assert_eq!(
-            deserialize_record(&res.unwrap().1, Record::Url, &Pubkey::default()).unwrap(),
+            deserialize_record(
+                &res.unwrap().1,
+                Record::Url,
+                &Pubkey::default(),
+                &Pubkey::default()
+            )
+            .unwrap()
+            .to_string(),
             "https://sns.id"
         );
 
@@ -505,7 +762,14 @@ mod tests {
 
         let res = resolve_record(&client, "🍍", Record::Eth).await.unwrap();
         assert_eq!(
-            deserialize_record(&res.unwrap().1, Record::Eth, &Pubkey::default()).unwrap(),
+            deserialize_record(
+                &res.unwrap().1,
+                Record::Eth,
+                &Pubkey::default(),
+                &Pubkey::default()
+            )
+            .unwrap()
+            .to_string(),
             "0x570eDC13f9D406a2b4E6477Ddf75D5E9cCF51cd6"
         );
     }
@@ -547,4 +811,375 @@ mod tests {
         let domains = get_tokenized_domains(&client, &owner).await.unwrap();
         println!("{domains:?}");
     }
+
+    #[tokio::test]
+    async fn offline_resolve_owner() {
+        let owner = Keypair::new();
+        let target = Pubkey::new_unique();
+        let holder = Pubkey::new_unique();
+        let fetcher = LedgerBuilder::new()
+            .domain("plain", owner.pubkey())
+            .domain("signed", owner.pubkey())
+            .sol_record_v1("signed", &target, &owner)
+            .domain("badly-signed", owner.pubkey())
+            .sol_record_v1("badly-signed", &target, &Keypair::new())
+            .domain("tokenized", owner.pubkey())
+            .tokenize("tokenized", holder)
+            .build();
+
+        let resolve = |domain| resolve_owner(&fetcher, domain);
+        assert_eq!(resolve("plain").await.unwrap(), Some(owner.pubkey()));
+        assert_eq!(resolve("signed").await.unwrap(), Some(target));
+        assert_eq!(resolve("badly-signed").await.unwrap(), Some(owner.pubkey()));
+        assert_eq!(resolve("tokenized").await.unwrap(), Some(holder));
+        assert_eq!(resolve("missing").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn offline_resolve_owner_detailed() {
+        use crate::config::SnsConfig;
+        use crate::test_utils::MockRecordV2;
+        use sns_records::state::validation::Validation;
+
+        let owner = Keypair::new();
+        let target = Pubkey::new_unique();
+        let holder = Pubkey::new_unique();
+        let config = SnsConfig {
+            warp_program_id: Some(Pubkey::new_unique()),
+            ..SnsConfig::DEFAULT
+        };
+        let sol_record_v2 = |roa: &Pubkey| {
+            MockRecordV2::new(Record::Sol, &target.to_string())
+                .staleness(&owner.pubkey())
+                .roa(Validation::Solana, roa.as_ref())
+        };
+
+        let fetcher = LedgerBuilder::with_config(config.clone())
+            .domain("plain", owner.pubkey())
+            .domain("signed", owner.pubkey())
+            .sol_record_v1("signed", &target, &owner)
+            .domain("verified", owner.pubkey())
+            .sol_record_v1("verified", &Pubkey::new_unique(), &owner)
+            .record_v2("verified", Record::Sol, sol_record_v2(&target))
+            .domain("unverified", owner.pubkey())
+            .record_v2("unverified", Record::Sol, sol_record_v2(&owner.pubkey()))
+            .domain("unverified-v1", owner.pubkey())
+            .record_v2("unverified-v1", Record::Sol, sol_record_v2(&owner.pubkey()))
+            .sol_record_v1("unverified-v1", &target, &owner)
+            .domain("stale", owner.pubkey())
+            .record_v2(
+                "stale",
+                Record::Sol,
+                MockRecordV2::new(Record::Sol, &target.to_string())
+                    .staleness(&Pubkey::new_unique())
+                    .roa(Validation::Solana, target.as_ref()),
+            )
+            .domain("tokenized", owner.pubkey())
+            .tokenize("tokenized", holder)
+            .domain("bridged", owner.pubkey())
+            .bridge("bridged", 2, [7; 32])
+            .domain("unbridged", config.warp_emitter)
+            .domain("bad-records", owner.pubkey())
+            .record_v2("bad-records", Record::Sol, MockRecordV2::raw(&[1, 2, 3]))
+            .record_v1("bad-records", Record::Sol, &[1, 2, 3])
+            .domain("bad-v2", owner.pubkey())
+            .record_v2(
+                "bad-v2",
+                Record::Sol,
+                MockRecordV2::raw(&[1, 2, 3]).staleness(&owner.pubkey()),
+            )
+            .sol_record_v1("bad-v2", &target, &owner)
+            .build();
+
+        let resolve = |domain| resolve_owner_detailed(&fetcher, domain);
+        assert_eq!(
+            resolve("plain").await.unwrap(),
+            Some(ResolvedOwner::Registry(owner.pubkey()))
+        );
+        assert_eq!(
+            resolve("signed").await.unwrap(),
+            Some(ResolvedOwner::SolRecordV1(target))
+        );
+        let verified = ResolvedOwner::SolRecordV2 {
+            owner: target,
+            verified: true,
+            registry_owner: owner.pubkey(),
+        };
+        assert_eq!(resolve("verified").await.unwrap(), Some(verified));
+        assert_eq!(verified.pubkey(), Some(target));
+        let unverified = ResolvedOwner::SolRecordV2 {
+            owner: target,
+            verified: false,
+            registry_owner: owner.pubkey(),
+        };
+        assert_eq!(resolve("unverified").await.unwrap(), Some(unverified));
+        // Unverified content is not trusted
+        assert_eq!(unverified.pubkey(), Some(owner.pubkey()));
+        assert_eq!(
+            resolve("unverified-v1").await.unwrap(),
+            Some(ResolvedOwner::SolRecordV1(target))
+        );
+        assert_eq!(
+            resolve("stale").await.unwrap(),
+            Some(ResolvedOwner::Registry(owner.pubkey()))
+        );
+
+        let mint = config.get_domain_mint(&config.get_domain_key("tokenized").unwrap());
+        let resolved = resolve("tokenized").await.unwrap().unwrap();
+        assert_eq!(
+            resolved,
+            ResolvedOwner::Tokenized {
+                owner: holder,
+                mint,
+                token_account: spl_associated_token_account::get_associated_token_address(
+                    &holder, &mint
+                ),
+            }
+        );
+        assert_eq!(resolved.pubkey(), Some(holder));
+
+        let resolved = resolve("bridged").await.unwrap().unwrap();
+        assert_eq!(
+            resolved,
+            ResolvedOwner::CrossChain {
+                chain: 2,
+                address: [7; 32]
+            }
+        );
+        assert_eq!(resolved.pubkey(), None);
+        // Without its XDomain account, a bridged domain resolves to the warp emitter
+        assert_eq!(
+            resolve("unbridged").await.unwrap(),
+            Some(ResolvedOwner::Registry(config.warp_emitter))
+        );
+
+        // Records which cannot be read are skipped
+        assert_eq!(
+            resolve("bad-records").await.unwrap(),
+            Some(ResolvedOwner::Registry(owner.pubkey()))
+        );
+        assert_eq!(
+            resolve("bad-v2").await.unwrap(),
+            Some(ResolvedOwner::SolRecordV1(target))
+        );
+        assert_eq!(resolve("missing").await.unwrap(), None);
+
+        // Bridged domains are not resolved to the warp emitter without the warp program
+        let fetcher = fetcher.with_config(SnsConfig::DEFAULT);
+        assert!(matches!(
+            resolve_owner_detailed(&fetcher, "bridged").await,
+            Err(SnsError::WarpProgramNotSet)
+        ));
+        assert_eq!(
+            resolve_owner_detailed(&fetcher, "plain").await.unwrap(),
+            Some(ResolvedOwner::Registry(owner.pubkey()))
+        );
+    }
+
+    #[tokio::test]
+    async fn offline_resolve_xchain_owner() {
+        use crate::config::SnsConfig;
+
+        let owner = Pubkey::new_unique();
+        let mut evm_owner = [0; 32];
+        evm_owner[12..].copy_from_slice(&[7; 20]);
+        let config = SnsConfig {
+            warp_program_id: Some(Pubkey::new_unique()),
+            ..SnsConfig::DEFAULT
+        };
+        let fetcher = LedgerBuilder::with_config(config)
+            .domain("plain", owner)
+            .domain("bridged", owner)
+            .bridge("bridged", 30, evm_owner)
+            .domain("solana-bridged", owner)
+            .bridge("solana-bridged", 1, [7; 32])
+            .build();
+
+        let owner = resolve_xchain_owner(&fetcher, "bridged")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            owner,
+            XChainOwner {
+                chain: 30,
+                address: evm_owner
+            }
+        );
+        assert_eq!(owner.evm_address(), Some([7; 20]));
+        let owner = resolve_xchain_owner(&fetcher, "solana-bridged")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(owner.evm_address(), None);
+        assert_eq!(resolve_xchain_owner(&fetcher, "plain").await.unwrap(), None);
+        assert_eq!(
+            resolve_xchain_owner(&fetcher, "missing").await.unwrap(),
+            None
+        );
+
+        // The XDomain accounts cannot be found without the warp program
+        let fetcher = fetcher.with_config(SnsConfig::DEFAULT);
+        assert!(matches!(
+            resolve_xchain_owner(&fetcher, "bridged").await,
+            Err(SnsError::WarpProgramNotSet)
+        ));
+    }
+
+    #[tokio::test]
+    async fn offline_reverse_batch_mixed() {
+        use crate::derivation::get_reverse_key;
+        use solana_sdk::account::Account;
+
+        let owner = Pubkey::new_unique();
+        let registry = |parent: Pubkey, data: &[u8]| {
+            let header = NameRecordHeader {
+                parent_name: parent,
+                owner,
+                class: Pubkey::default(),
+            };
+            let mut buffer = vec![0; NameRecordHeader::LEN];
+            header.pack_into_slice(&mut buffer);
+            buffer.extend(data);
+            Account {
+                data: buffer,
+                owner: spl_name_service::ID,
+                ..Account::default()
+            }
+        };
+        let (not_registry, short_registry, orphan, foreign_child) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let fetcher = LedgerBuilder::new()
+            .domain("bonfida", owner)
+            .domain("dex.bonfida", owner)
+            .domain("sub.dex.bonfida", owner)
+            .domain("corrupt", owner)
+            .account(
+                get_reverse_key("corrupt").unwrap(),
+                registry(Pubkey::default(), &[0xff; 2]),
+            )
+            .account(
+                not_registry,
+                Account {
+                    data: vec![1, 2, 3],
+                    ..Account::default()
+                },
+            )
+            .account(
+                short_registry,
+                Account {
+                    data: vec![0; 10],
+                    owner: spl_name_service::ID,
+                    ..Account::default()
+                },
+            )
+            .account(orphan, registry(Pubkey::default(), &[]))
+            .account(foreign_child, registry(not_registry, &[]))
+            .build();
+
+        let keys = [
+            get_domain_key("bonfida").unwrap(),
+            Pubkey::new_unique(),
+            not_registry,
+            short_registry,
+            orphan,
+            foreign_child,
+            get_domain_key("corrupt").unwrap(),
+            get_domain_key("sub.dex.bonfida").unwrap(),
+        ];
+        let res = resolve_reverse_batch(&fetcher, &keys).await.unwrap();
+        assert_eq!(
+            res,
+            [
+                Some("bonfida".to_owned()),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                Some("sub.dex.bonfida".to_owned()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn offline_domains() {
+        let owner = Pubkey::new_unique();
+        let holder = Pubkey::new_unique();
+        let fetcher = LedgerBuilder::new()
+            .domain("bonfida", owner)
+            .domain("dex.bonfida", owner)
+            .domain("naming.bonfida", owner)
+            .domain("sub.dex.bonfida", owner)
+            .domain("other", Pubkey::new_unique())
+            .domain("tokenized", owner)
+            .tokenize("tokenized", holder)
+            .favourite_domain(&owner, "bonfida")
+            .build();
+
+        let bonfida = get_domain_key("bonfida").unwrap();
+        let mut subs = get_subdomains(&fetcher, &bonfida).await.unwrap();
+        subs.sort();
+        assert_eq!(subs, ["dex", "naming"]);
+
+        let reverse = resolve_reverse(&fetcher, &get_domain_key("sub.dex.bonfida").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(reverse.unwrap(), "sub.dex.bonfida");
+
+        assert_eq!(get_domains_owner(&fetcher, owner).await.unwrap(), [bonfida]);
+        assert_eq!(
+            get_tokenized_domains(&fetcher, &holder).await.unwrap(),
+            [("tokenized".to_owned(), get_domain_key("tokenized").unwrap())]
+        );
+        assert_eq!(
+            get_favourite_domain(&fetcher, &owner).await.unwrap(),
+            Some(bonfida)
+        );
+
+        let fetcher = LedgerBuilder::new()
+            .domain("bonfida", owner)
+            .record_v1("bonfida", Record::Url, b"https://sns.id")
+            .build();
+        let (_, data) = resolve_record(&fetcher, "bonfida", Record::Url)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            deserialize_record(&data, Record::Url, &Pubkey::default(), &Pubkey::default())
+                .unwrap()
+                .to_string(),
+            "https://sns.id"
+        );
+    }
+
+    #[tokio::test]
+    async fn offline_clusters() {
+        use crate::config::{Cluster, SnsConfig};
+        use crate::fetcher::ClusterFetcher;
+
+        let owner = Pubkey::new_unique();
+        let devnet = LedgerBuilder::with_config(Cluster::Devnet)
+            .domain("bonfida", owner)
+            .domain("dex.bonfida", owner)
+            .build();
+        assert_eq!(
+            resolve_owner(&devnet, "bonfida").await.unwrap(),
+            Some(owner)
+        );
+        let key = SnsConfig::DEVNET.get_domain_key("dex.bonfida").unwrap();
+        assert_eq!(
+            resolve_reverse(&devnet, &key).await.unwrap().unwrap(),
+            "dex.bonfida"
+        );
+
+        // The same accounts read with the mainnet config
+        let mainnet = ClusterFetcher::new(devnet, Cluster::Mainnet);
+        assert_eq!(resolve_owner(&mainnet, "bonfida").await.unwrap(), None);
+    }
 }
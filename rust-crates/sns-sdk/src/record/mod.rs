@@ -1,16 +1,17 @@
-use crate::{
-    derivation::{derive, get_prefix, trim_tld, Domain, ROOT_DOMAIN_ACCOUNT},
-    error::SnsError,
-};
+use self::value::RecordValue;
+use crate::{config::SnsConfig, derivation::Domain, error::SnsError};
 use sns_records::state::validation::Validation;
 use solana_program::pubkey;
+use std::collections::HashMap;
 use {bech32::u5, solana_program::pubkey::Pubkey};
 pub mod record_v1;
 pub mod record_v2;
+pub mod validate;
+pub mod value;
 
 pub const CENTRAL_STATE_RECORD_V2: Pubkey = pubkey!("2pMnqHvei2N5oDcVGCRdZx48gqti199wr5CsyTTafsbo");
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Record {
     Ipfs,
     Arwv,
@@ -36,109 +37,409 @@ pub enum Record {
     AAAA,
     CNAME,
     TXT,
+    Background,
     BASE,
+    IPNS,
+    Polygon,
+    Arbitrum,
+    Sui,
+    Aptos,
+    Stellar,
+    Ton,
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum RecordVersion {
     V1 = 1,
     V2 = 2,
 }
 
+/// How the content of a record is serialized
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RecordEncoding {
+    Utf8,
+    /// UTF-8 string encoded with punycode
+    Punycode,
+    /// A Solana public key, signed by the domain owner in SOL records V1
+    Pubkey,
+    /// A 20 bytes EVM address, `0x` prefixed hex string
+    Evm,
+    /// A 20 bytes Injective address, bech32 string with the `inj` prefix
+    Injective,
+    /// A UTF-8 Bitcoin address, either base58check or segwit
+    Bitcoin,
+    Ipv4,
+    Ipv6,
+}
+
+/// The properties of a record
+#[derive(Copy, Clone, Debug)]
+pub struct RecordInfo {
+    pub record: Record,
+    pub name: &'static str,
+    pub encoding: RecordEncoding,
+    /// The size of the content of a record V1, `None` for records V1 holding their content as a
+    /// UTF-8 string
+    pub v1_size: Option<usize>,
+    /// The validation required for the right of association of a record V2, performed by the
+    /// content itself unless the record has guardians, see `SnsConfig::get_guardians`
+    pub roa_validation: Validation,
+}
+
+/// The properties of every record, in the order of the `Record` variants
+pub const RECORDS: [RecordInfo; 33] = [
+    RecordInfo {
+        record: Record::Ipfs,
+        name: "IPFS",
+        encoding: RecordEncoding::Utf8,
+        v1_size: None,
+        roa_validation: Validation::None,
+    },
+    RecordInfo {
+        record: Record::Arwv,
+        name: "ARWV",
+        encoding: RecordEncoding::Utf8,
+        v1_size: None,
+        roa_validation: Validation::None,
+    },
+    RecordInfo {
+        record: Record::Sol,
+        name: "SOL",
+        encoding: RecordEncoding::Pubkey,
+        v1_size: Some(96),
+        roa_validation: Validation::Solana,
+    },
+    RecordInfo {
+        record: Record::Eth,
+        name: "ETH",
+        encoding: RecordEncoding::Evm,
+        v1_size: Some(20),
+        roa_validation: Validation::Ethereum,
+    },
+    RecordInfo {
+        record: Record::Btc,
+        name: "BTC",
+        encoding: RecordEncoding::Bitcoin,
+        v1_size: None,
+        roa_validation: Validation::None,
+    },
+    RecordInfo {
+        record: Record::Ltc,
+        name: "LTC",
+        encoding: RecordEncoding::Utf8,
+        v1_size: None,
+        roa_validation: Validation::None,
+    },
+    RecordInfo {
+        record: Record::Doge,
+        name: "DOGE",
+        encoding: RecordEncoding::Utf8,
+        v1_size: None,
+        roa_validation: Validation::None,
+    },
+    RecordInfo {
+        record: Record::Email,
+        name: "email",
+        encoding: RecordEncoding::Utf8,
+        v1_size: None,
+        roa_validation: Validation::None,
+    },
+    RecordInfo {
+        record: Record::Url,
+        name: "url",
+        encoding: RecordEncoding::Utf8,
+        v1_size: None,
+        roa_validation: Validation::Solana,
+    },
+    RecordInfo {
+        record: Record::Discord,
+        name: "discord",
+        encoding: RecordEncoding::Utf8,
+        v1_size: None,
+        roa_validation: Validation::None,
+    },
+    RecordInfo {
+        record: Record::Github,
+        name: "github",
+        encoding: RecordEncoding::Utf8,
+        v1_size: None,
+        roa_validation: Validation::None,
+    },
+    RecordInfo {
+        record: Record::Reddit,
+        name: "reddit",
+        encoding: RecordEncoding::Utf8,
+        v1_size: None,
+        roa_validation: Validation::None,
+    },
+    RecordInfo {
+        record: Record::Twitter,
+        name: "twitter",
+        encoding: RecordEncoding::Utf8,
+        v1_size: None,
+        roa_validation: Validation::None,
+    },
+    RecordInfo {
+        record: Record::Telegram,
+        name: "telegram",
+        encoding: RecordEncoding::Utf8,
+        v1_size: None,
+        roa_validation: Validation::None,
+    },
+    RecordInfo {
+        record: Record::Pic,
+        name: "pic",
+        encoding: RecordEncoding::Utf8,
+        v1_size: None,
+        roa_validation: Validation::None,
+    },
+    RecordInfo {
+        record: Record::Shdw,
+        name: "SHDW",
+        encoding: RecordEncoding::Utf8,
+        v1_size: None,
+        roa_validation: Validation::None,
+    },
+    RecordInfo {
+        record: Record::Point,
+        name: "POINT",
+        encoding: RecordEncoding::Utf8,
+        v1_size: None,
+        roa_validation: Validation::None,
+    },
+    RecordInfo {
+        record: Record::Bsc,
+        name: "BSC",
+        encoding: RecordEncoding::Evm,
+        v1_size: Some(20),
+        roa_validation: Validation::Ethereum,
+    },
+    RecordInfo {
+        record: Record::Injective,
+        name: "INJ",
+        encoding: RecordEncoding::Injective,
+        v1_size: Some(20),
+        roa_validation: Validation::Ethereum,
+    },
+    RecordInfo {
+        record: Record::Backpack,
+        name: "backpack",
+        encoding: RecordEncoding::Utf8,
+        v1_size: None,
+        roa_validation: Validation::None,
+    },
+    RecordInfo {
+        record: Record::A,
+        name: "A",
+        encoding: RecordEncoding::Ipv4,
+        v1_size: Some(4),
+        roa_validation: Validation::None,
+    },
+    RecordInfo {
+        record: Record::AAAA,
+        name: "AAAA",
+        encoding: RecordEncoding::Ipv6,
+        v1_size: Some(16),
+        roa_validation: Validation::None,
+    },
+    RecordInfo {
+        record: Record::CNAME,
+        name: "CNAME",
+        encoding: RecordEncoding::Punycode,
+        v1_size: None,
+        roa_validation: Validation::Solana,
+    },
+    RecordInfo {
+        record: Record::TXT,
+        name: "TXT",
+        encoding: RecordEncoding::Punycode,
+        v1_size: None,
+        roa_validation: Validation::None,
+    },
+    RecordInfo {
+        record: Record::Background,
+        name: "background",
+        encoding: RecordEncoding::Pubkey,
+        v1_size: Some(32),
+        roa_validation: Validation::None,
+    },
+    RecordInfo {
+        record: Record::BASE,
+        name: "BASE",
+        encoding: RecordEncoding::Evm,
+        v1_size: None,
+        roa_validation: Validation::Ethereum,
+    },
+    RecordInfo {
+        record: Record::IPNS,
+        name: "IPNS",
+        encoding: RecordEncoding::Utf8,
+        v1_size: None,
+        roa_validation: Validation::None,
+    },
+    // The following records have no SNS-IP nor JS SDK definition yet, their content is stored as
+    // a UTF-8 string which is validated on write, see `validate_record_content`
+    RecordInfo {
+        record: Record::Polygon,
+        name: "POLYGON",
+        encoding: RecordEncoding::Utf8,
+        v1_size: None,
+        roa_validation: Validation::None,
+    },
+    RecordInfo {
+        record: Record::Arbitrum,
+        name: "ARB",
+        encoding: RecordEncoding::Utf8,
+        v1_size: None,
+        roa_validation: Validation::None,
+    },
+    RecordInfo {
+        record: Record::Sui,
+        name: "SUI",
+        encoding: RecordEncoding::Utf8,
+        v1_size: None,
+        roa_validation: Validation::None,
+    },
+    RecordInfo {
+        record: Record::Aptos,
+        name: "APT",
+        encoding: RecordEncoding::Utf8,
+        v1_size: None,
+        roa_validation: Validation::None,
+    },
+    RecordInfo {
+        record: Record::Stellar,
+        name: "XLM",
+        encoding: RecordEncoding::Utf8,
+        v1_size: None,
+        roa_validation: Validation::None,
+    },
+    RecordInfo {
+        record: Record::Ton,
+        name: "TON",
+        encoding: RecordEncoding::Utf8,
+        v1_size: None,
+        roa_validation: Validation::None,
+    },
+];
+
 impl Record {
+    pub fn all() -> impl Iterator<Item = Record> {
+        RECORDS.iter().map(|info| info.record)
+    }
+
+    pub fn info(&self) -> &'static RecordInfo {
+        &RECORDS[*self as usize]
+    }
+
     pub fn as_str(&self) -> &'static str {
-        match self {
-            Record::Ipfs => "IPFS",
-            Record::Arwv => "ARWV",
-            Record::Sol => "SOL",
-            Record::Eth => "ETH",
-            Record::Btc => "BTC",
-            Record::Ltc => "LTC",
-            Record::Doge => "DOGE",
-            Record::Email => "email",
-            Record::Url => "url",
-            Record::Discord => "discord",
-            Record::Github => "github",
-            Record::Reddit => "reddit",
-            Record::Twitter => "twitter",
-            Record::Telegram => "telegram",
-            Record::Pic => "pic",
-            Record::Shdw => "SHDW",
-            Record::Point => "POINT",
-            Record::Bsc => "BSC",
-            Record::Injective => "INJ",
-            Record::Backpack => "backpack",
-            Record::A => "A",
-            Record::AAAA => "AAAA",
-            Record::CNAME => "CNAME",
-            Record::TXT => "TXT",
-            Record::BASE => "BASE",
-        }
+        self.info().name
     }
 
     pub fn try_from_str(input: &str) -> Result<Record, SnsError> {
-        match input {
-            "IPFS" => Ok(Record::Ipfs),
-            "ARWV" => Ok(Record::Arwv),
-            "SOL" => Ok(Record::Sol),
-            "ETH" => Ok(Record::Eth),
-            "BTC" => Ok(Record::Btc),
-            "LTC" => Ok(Record::Ltc),
-            "DOGE" => Ok(Record::Doge),
-            "email" => Ok(Record::Email),
-            "url" => Ok(Record::Url),
-            "discord" => Ok(Record::Discord),
-            "github" => Ok(Record::Github),
-            "reddit" => Ok(Record::Reddit),
-            "twitter" => Ok(Record::Twitter),
-            "telegram" => Ok(Record::Telegram),
-            "pic" => Ok(Record::Pic),
-            "SHDW" => Ok(Record::Shdw),
-            "POINT" => Ok(Record::Point),
-            "BSC" => Ok(Record::Bsc),
-            "INJ" => Ok(Record::Injective),
-            "backpack" => Ok(Record::Backpack),
-            "A" => Ok(Record::A),
-            "AAAA" => Ok(Record::AAAA),
-            "CNAME" => Ok(Record::CNAME),
-            "TXT" => Ok(Record::TXT),
-            "BASE" => Ok(Record::BASE),
-            _ => Err(SnsError::UnrecognizedRecord),
-        }
+        RECORDS
+            .iter()
+            .find(|info| info.name == input)
+            .map(|info| info.record)
+            .ok_or(SnsError::UnrecognizedRecord)
+    }
+
+    pub fn encoding(&self) -> RecordEncoding {
+        self.info().encoding
     }
 
     pub fn utf8_encoded(&self) -> bool {
         matches!(
-            self,
-            Record::Ipfs
-                | Record::Arwv
-                | Record::Ltc
-                | Record::Doge
-                | Record::Email
-                | Record::Url
-                | Record::Discord
-                | Record::Github
-                | Record::Reddit
-                | Record::Twitter
-                | Record::Telegram
-                | Record::Pic
-                | Record::Shdw
-                | Record::Point
-                | Record::Backpack
-                | Record::TXT
-                | Record::CNAME
+            self.encoding(),
+            RecordEncoding::Utf8 | RecordEncoding::Punycode | RecordEncoding::Bitcoin
         )
     }
 
     pub fn roa_validation(&self) -> Validation {
+        self.info().roa_validation
+    }
+}
+
+/// The name of a record, either a known `Record` or an arbitrary name
+///
+/// The records program accepts any record name, the content of custom records is stored as
+/// raw bytes.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum RecordKey {
+    Known(Record),
+    Custom(String),
+}
+
+impl RecordKey {
+    /// Parses a record name, known names are mapped to their `Record`
+    pub fn new(name: &str) -> Self {
+        match Record::try_from_str(name) {
+            Ok(record) => Self::Known(record),
+            Err(_) => Self::Custom(name.to_owned()),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Known(record) => record.as_str(),
+            Self::Custom(name) => name,
+        }
+    }
+
+    pub fn record(&self) -> Option<Record> {
         match self {
-            Record::Sol | Record::CNAME | Record::Url => Validation::Solana,
-            Record::Injective | Record::Eth | Record::Bsc | Record::BASE => Validation::Ethereum,
-            _ => Validation::None,
+            Self::Known(record) => Some(*record),
+            Self::Custom(_) => None,
         }
     }
 }
 
+impl From<Record> for RecordKey {
+    fn from(record: Record) -> Self {
+        Self::Known(record)
+    }
+}
+
+impl From<&str> for RecordKey {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl std::fmt::Display for RecordKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The trust level of a resolved record
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RecordStatus {
+    /// Record V2 signed by the current owner of the domain, with a valid right of association
+    Verified,
+    /// Record V2 signed by the current owner of the domain, the right of association is missing
+    /// or invalid
+    UnverifiedRoa,
+    /// Record V2 signed by a previous owner of the domain
+    Stale,
+    /// Record V1, which carries no verification
+    Unverified,
+    /// Record whose content cannot be read, it is returned as `RecordValue::Raw`
+    Invalid,
+}
+
+/// A record value along with the account it was read from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResolvedRecord {
+    pub record: Record,
+    pub version: RecordVersion,
+    pub content: RecordValue,
+    pub status: RecordStatus,
+}
+
+/// Resolved records of each domain, in the order of the domains passed to `get_records_batch`
+pub type RecordsBatch = Vec<Option<HashMap<Record, ResolvedRecord>>>;
+
 pub fn get_record_class(record_version: RecordVersion) -> Option<Pubkey> {
     match record_version {
         RecordVersion::V2 => Some(CENTRAL_STATE_RECORD_V2),
@@ -148,47 +449,36 @@ pub fn get_record_class(record_version: RecordVersion) -> Option<Pubkey> {
 
 pub fn get_record_key(
     domain: &str,
-    record: Record,
+    record: impl Into<RecordKey>,
     record_version: RecordVersion,
 ) -> Result<Pubkey, SnsError> {
-    let domain = trim_tld(domain);
-    let splitted = domain.split('.').collect::<Vec<_>>();
-    match splitted.len() {
-        1 => {
-            let parent = derive(domain, &ROOT_DOMAIN_ACCOUNT, None);
-            let prefix = get_prefix(Domain::Record(record_version));
-            let key = derive(
-                &format!("{}{}", prefix, record.as_str()),
-                &parent,
-                get_record_class(record_version),
-            );
-            Ok(key)
-        }
-        2 => {
-            let parent = derive(splitted[1], &ROOT_DOMAIN_ACCOUNT, None);
-            let sub_domain = get_prefix(Domain::Sub) + splitted[1];
-            let sub_key = derive(&sub_domain, &parent, None);
-
-            let record_prefix = get_prefix(Domain::Record(record_version));
-            let key = derive(
-                &format!("{record_prefix}{}", record.as_str()),
-                &sub_key,
-                get_record_class(record_version),
-            );
-            Ok(key)
-        }
-        _ => Err(SnsError::InvalidDomain),
-    }
+    SnsConfig::DEFAULT.get_record_key(domain, record, record_version)
 }
 
-pub fn get_record_v2_key(domain: &str, record: Record) -> Result<Pubkey, SnsError> {
+pub fn get_record_v2_key(domain: &str, record: impl Into<RecordKey>) -> Result<Pubkey, SnsError> {
     get_record_key(domain, record, RecordVersion::V2)
 }
 
-pub fn get_record_v1_key(domain: &str, record: Record) -> Result<Pubkey, SnsError> {
+pub fn get_record_v1_key(domain: &str, record: impl Into<RecordKey>) -> Result<Pubkey, SnsError> {
     get_record_key(domain, record, RecordVersion::V1)
 }
 
+impl SnsConfig {
+    pub fn get_record_key(
+        &self,
+        domain: &str,
+        record: impl Into<RecordKey>,
+        record_version: RecordVersion,
+    ) -> Result<Pubkey, SnsError> {
+        self.derive_child(
+            domain,
+            Domain::Record(record_version),
+            record.into().as_str(),
+            get_record_class(record_version),
+        )
+    }
+}
+
 pub fn convert_u5_array(u5_data: &[u5]) -> Vec<u8> {
     let mut u8_data: Vec<u8> = Vec::new();
     let mut buffer: u16 = 0;
@@ -227,4 +517,84 @@ mod test {
             v2
         );
     }
+
+    #[test]
+    fn test_get_record_key_subdomain() {
+        // Expected keys are taken from the JS SDK `getRecordKeySync` and `getRecordV2Key` tests
+        let items = [
+            (
+                "domain1.sol",
+                Record::Sol,
+                pubkey!("ATH9akc5pi1PWDB39YY7VCoYzCxmz8XVj23oegSoNSPL"),
+                pubkey!("GBrd6Q53eu1T2PiaQAtm92r3DwxmoGvZ2D6xjtVtN1Qt"),
+            ),
+            (
+                "sub.domain2.sol",
+                Record::Sol,
+                pubkey!("AEgJVf6zaQfkyYPnYu8Y9Vxa1Sy69EtRSP8iGubx5MnC"),
+                pubkey!("A3EFmyCmK5rp73TdgLH8aW49PJ8SJw915arhydRZ6Sws"),
+            ),
+            (
+                "domain3.sol",
+                Record::Url,
+                pubkey!("EuxtWLCKsdpwM8ftKjnD2Q8vBdzZunh7DY1mHwXhLTqx"),
+                pubkey!("DMZmnjcAnUwSje4o2LGJhipCfNZ5b37GEbbkwbQBWEW1"),
+            ),
+            (
+                "sub.domain4.sol",
+                Record::Url,
+                pubkey!("64nv6HSbifdUgdWst48V4YUB3Y3uQXVQRD4iDZPd9qGx"),
+                pubkey!("6o8JQ7vss6r9sw9GWNVugZktwfEJ67iUz6H63hhmg4sj"),
+            ),
+            (
+                "domain5.sol",
+                Record::Ipfs,
+                pubkey!("2uRMeYzKXaYgFVQ1Yh7fKyZWcxsFUMgpEwMi19sVjwjk"),
+                pubkey!("DQHeVmAj9Nz4uAn2dneEsgBZWcfhUqLdtbDcfWhGL47D"),
+            ),
+            (
+                "sub.domain6.sol",
+                Record::Ipfs,
+                pubkey!("61JdnEhbd2bEfxnu2uQ38gM2SUry2yY8kBMEseYh8dDy"),
+                pubkey!("Dj7tnTTaktrrmdtatRuLG3YdtGZk8XEBMb4w5WtCBHvr"),
+            ),
+        ];
+        for (domain, record, v1, v2) in items {
+            assert_eq!(get_record_v1_key(domain, record).unwrap(), v1, "{domain}");
+            assert_eq!(get_record_v2_key(domain, record).unwrap(), v2, "{domain}");
+        }
+    }
+
+    #[test]
+    fn test_records_table() {
+        assert_eq!(Record::all().count(), RECORDS.len());
+        for (idx, record) in Record::all().enumerate() {
+            assert_eq!(record as usize, idx);
+            assert_eq!(Record::try_from_str(record.as_str()).unwrap(), record);
+            // Only the binary encoded records have a fixed size, although some of them are UTF-8
+            // strings in records V1 (e.g `BASE`)
+            assert!(!record.utf8_encoded() || record.info().v1_size.is_none());
+        }
+        assert!(Record::try_from_str("sol").is_err());
+    }
+
+    #[test]
+    fn test_custom_record_key() {
+        assert_eq!(RecordKey::new("SOL"), RecordKey::Known(Record::Sol));
+        let custom = RecordKey::new("x-ourapp-config");
+        assert_eq!(custom, RecordKey::Custom("x-ourapp-config".to_owned()));
+        assert_eq!(custom.to_string(), "x-ourapp-config");
+        assert_eq!(custom.record(), None);
+
+        let domain_key = crate::derivation::get_domain_key("bonfida").unwrap();
+        let v1 = crate::derivation::derive("\x01x-ourapp-config", &domain_key, None);
+        assert_eq!(get_record_v1_key("bonfida", custom.clone()).unwrap(), v1);
+        let (v2, _) =
+            sns_records::utils::get_record_key_and_seeds(&domain_key, "\x02x-ourapp-config");
+        assert_eq!(get_record_v2_key("bonfida", custom).unwrap(), v2);
+        assert_eq!(
+            get_record_v2_key("bonfida", "url").unwrap(),
+            get_record_v2_key("bonfida", Record::Url).unwrap()
+        );
+    }
 }
@@ -1,12 +1,20 @@
-use super::Record;
-use crate::error::SnsError;
+pub use super::convert_u5_array;
+use super::{
+    validate::validate_record_content, value::RecordValue, Record, RecordKey, RecordVersion,
+};
+use crate::{
+    config::SnsConfig,
+    derivation::{get_hashed_name, get_prefix, Domain},
+    error::SnsError,
+};
 use {
-    bech32,
-    bech32::u5,
-    bech32::ToBase32,
     ed25519_dalek,
-    solana_program::pubkey::Pubkey,
-    std::net::{Ipv4Addr, Ipv6Addr},
+    solana_program::{instruction::Instruction, program_pack::Pack, pubkey::Pubkey, rent::Rent},
+    solana_sdk::signer::Signer,
+    spl_name_service::{
+        instruction::{create, delete, realloc, update, NameRegistryInstruction},
+        state::NameRecordHeader,
+    },
 };
 
 pub fn check_sol_record(
@@ -21,175 +29,255 @@ pub fn check_sol_record(
 }
 
 pub fn get_record_size(record: Record) -> Option<usize> {
-    match record {
-        Record::Sol => Some(96),
-        Record::Eth | Record::Bsc | Record::Injective => Some(20),
-        Record::A => Some(4),
-        Record::AAAA => Some(16),
-        _ => None,
-    }
+    record.info().v1_size
 }
 
+/// Deserializes the content of a record V1
+///
+/// `signer` is the owner of the record account, which has to sign the content of SOL records.
 pub fn deserialize_record(
     data: &[u8],
     record: Record,
     record_key: &Pubkey,
-) -> Result<String, SnsError> {
-    let size = get_record_size(record);
-
-    if size.is_none() {
-        let des = String::from_utf8(data.to_vec())?
-            .trim_end_matches('\0')
-            .to_string();
-        return Ok(des);
+    signer: &Pubkey,
+) -> Result<RecordValue, SnsError> {
+    // SOL records are handled first, whether they are over allocated or not
+    if record == Record::Sol {
+        if let Some(content) = verify_sol_record(data, record_key, signer)? {
+            return Ok(RecordValue::Pubkey(content));
+        }
     }
 
-    let size = size.unwrap();
     let idx = data
         .iter()
         .rposition(|&byte| byte != 0)
         .map_or(0, |pos| pos + 1);
 
-    // Old record UTF-8 encoded
-    if size != idx {
-        let address = String::from_utf8(data[0..idx].to_vec())?;
-        match record {
-            Record::Injective => {
-                let (prefix, data, _) = bech32::decode(&address)?;
-                if prefix == "inj" && data.len() == 32 {
-                    return Ok(address);
-                }
-            }
-            Record::Eth | Record::Bsc => {
-                let prefix = address.get(0..2).ok_or(SnsError::InvalidRecordData)?;
-                let hex = address.get(2..).ok_or(SnsError::InvalidRecordData)?;
-                let decoded = hex::decode(hex)?;
-                if prefix == "0x" && decoded.len() == 20 {
-                    return Ok(address);
-                }
-            }
-            Record::A => {
-                let des = address.parse::<Ipv4Addr>();
-                if des.is_ok() {
-                    return Ok(address);
-                }
-            }
-            Record::AAAA => {
-                let des = address.parse::<Ipv6Addr>();
-                if des.is_ok() {
-                    return Ok(address);
-                }
-            }
-            _ => {}
+    let Some(size) = get_record_size(record) else {
+        // Textual content is decoded like records V2, e.g `CNAME` and `TXT` are punycode encoded
+        if record.utf8_encoded() {
+            return RecordValue::from_bytes(record, &data[..idx]);
         }
-        return Err(SnsError::InvalidReverse);
+        return RecordValue::parse(record, &String::from_utf8(data[..idx].to_vec())?);
+    };
+
+    // Unsigned SOL records are invalid
+    if record == Record::Sol {
+        return Err(if size != idx {
+            SnsError::InvalidReverse
+        } else {
+            SnsError::InvalidRecordData
+        });
     }
 
-    // Properly sized record
-    match record {
-        Record::Sol => {
-            let signature = data.get(32..).ok_or(SnsError::InvalidRecordData)?;
-            let dst = data.get(0..32).ok_or(SnsError::InvalidRecordData)?;
-            let expected = [dst, &record_key.to_bytes()].concat();
-            let valid = check_sol_record(&expected, signature, *record_key)?;
-            if valid {
-                let pubkey = Pubkey::new_from_array(dst.try_into().unwrap());
-                return Ok(pubkey.to_string());
-            }
-        }
-        Record::Eth | Record::Bsc => {
-            let des = format!("0x{}", hex::encode(data));
-            return Ok(des);
-        }
-        Record::Injective => {
-            let des = bech32::encode("inj", data.to_base32(), bech32::Variant::Bech32)?;
-            return Ok(des);
-        }
-        Record::A => {
-            let bytes: [u8; 4] = data.try_into().unwrap();
-            let ip = Ipv4Addr::from(bytes);
-            return Ok(ip.to_string());
-        }
-        Record::AAAA => {
-            let bytes: [u8; 16] = data.try_into().unwrap();
-            let ip = Ipv6Addr::from(bytes);
-            return Ok(ip.to_string());
-        }
-        _ => {}
+    // Old record UTF-8 encoded, binary content can end with zeros so only the size tells them apart
+    if data.len() != size {
+        let address = String::from_utf8(data[0..idx].to_vec())?;
+        return RecordValue::parse(record, &address).map_err(|_| SnsError::InvalidReverse);
     }
 
-    Err(SnsError::InvalidRecordData)
+    // Properly sized record
+    RecordValue::from_bytes(record, data)
 }
 
+/// Serializes the content of a record V1, textual content is checked with
+/// `validate_record_content`
 pub fn serialize_record(content: &str, record: Record) -> Result<Vec<u8>, SnsError> {
-    let size = get_record_size(record);
+    validate_record_content(record, content)?;
+    serialize_record_unchecked(content, record)
+}
 
-    if size.is_none() {
-        match record {
-            Record::CNAME | Record::TXT => {
-                let encoded = punycode::encode(content).map_err(|_| SnsError::Punycode)?;
-                return Ok(encoded.as_bytes().to_vec());
-            }
-            _ => return Ok(content.as_bytes().to_vec()),
-        };
+/// Serializes the content of a record V1 without validating textual content
+pub fn serialize_record_unchecked(content: &str, record: Record) -> Result<Vec<u8>, SnsError> {
+    // The content of a SOL record is signed, see `serialize_sol_record`
+    if record == Record::Sol {
+        return Err(SnsError::SolRecordNotSupported);
     }
+    serialize_record_value(&RecordValue::parse(record, content)?, record)
+}
 
-    match record {
-        Record::Eth | Record::Bsc => {
-            if !content.starts_with("0x") {
-                return Err(SnsError::InvalidEvmAddress);
-            }
-            let decoded = hex::decode(content.get(2..).ok_or(SnsError::InvalidEvmAddress)?)?;
-            if decoded.len() != 20 {
-                return Err(SnsError::InvalidEvmAddress);
-            }
-            Ok(decoded)
-        }
-        Record::Injective => {
-            if !content.starts_with("inj") {
-                return Err(SnsError::InvalidInjectiveAddress);
-            }
-            let (_, data, _) = bech32::decode(content)?;
-            let data = convert_u5_array(&data);
-            if data.len() != 20 {
-                return Err(SnsError::InvalidInjectiveAddress);
-            }
-            Ok(data)
-        }
-        Record::A => {
-            let ip = content
-                .parse::<Ipv4Addr>()
-                .map_err(|_| SnsError::InvalidIpv4)?;
-            Ok(ip.octets().to_vec())
-        }
-        Record::AAAA => {
-            let ip = content
-                .parse::<Ipv6Addr>()
-                .map_err(|_| SnsError::InvalidIpv6)?;
-            Ok(ip.octets().to_vec())
-        }
-        Record::Sol => Err(SnsError::SolRecordNotSupported),
-        _ => unreachable!(),
+/// Serializes the content of a record V1, SOL records are serialized with `serialize_sol_record`
+pub fn serialize_record_value(value: &RecordValue, record: Record) -> Result<Vec<u8>, SnsError> {
+    if record == Record::Sol {
+        return Err(SnsError::SolRecordNotSupported);
+    }
+    let bytes = value.to_bytes(record)?;
+    if get_record_size(record).is_some()
+        || record.utf8_encoded()
+        || matches!(value, RecordValue::Raw(_))
+    {
+        return Ok(bytes);
+    }
+    // Records V1 without a size hold a UTF-8 string even when their V2 content is binary
+    // (e.g `BASE`)
+    Ok(value.to_string().into_bytes())
+}
+
+/// The message to sign with the domain owner to store `content` in the SOL record `record_key`
+pub fn get_sol_record_message(content: &Pubkey, record_key: &Pubkey) -> Vec<u8> {
+    hex::encode([content.as_ref(), record_key.as_ref()].concat()).into_bytes()
+}
+
+/// Returns the content of a SOL record if it was signed by `signer`
+///
+/// `data` is the content of the record account following the name registry header.
+pub fn verify_sol_record(
+    data: &[u8],
+    record_key: &Pubkey,
+    signer: &Pubkey,
+) -> Result<Option<Pubkey>, SnsError> {
+    let (Some(content), Some(signature)) = (data.get(..32), data.get(32..96)) else {
+        return Err(SnsError::InvalidRecordData);
+    };
+    let content = Pubkey::new_from_array(content.try_into()?);
+    let message = get_sol_record_message(&content, record_key);
+    // Keys which are not valid ed25519 points cannot sign
+    let valid = check_sol_record(&message, signature, *signer).unwrap_or(false);
+    Ok(valid.then_some(content))
+}
+
+/// Signs the content of a SOL record and serializes it
+pub fn sign_sol_record(
+    content: &Pubkey,
+    record_key: &Pubkey,
+    signer: &(impl Signer + ?Sized),
+) -> Result<Vec<u8>, SnsError> {
+    let signature = signer.try_sign_message(&get_sol_record_message(content, record_key))?;
+    serialize_sol_record(
+        content,
+        record_key,
+        &signer.try_pubkey()?,
+        signature.as_ref(),
+    )
+}
+
+/// Serializes a SOL record, `signature` is the signature of `get_sol_record_message` by `signer`
+pub fn serialize_sol_record(
+    content: &Pubkey,
+    record_key: &Pubkey,
+    signer: &Pubkey,
+    signature: &[u8],
+) -> Result<Vec<u8>, SnsError> {
+    let message = get_sol_record_message(content, record_key);
+    if !check_sol_record(&message, signature, *signer)? {
+        return Err(SnsError::InvalidSignature);
     }
+    Ok([content.as_ref(), signature].concat())
+}
+
+/// The lamports needed by a record V1 holding `data_len` bytes to be rent exempt
+pub fn get_record_v1_rent(data_len: usize) -> u64 {
+    Rent::default().minimum_balance(NameRecordHeader::LEN + data_len)
+}
+
+/// Creates a record V1 and writes `data` in it, `data` being serialized with `serialize_record`
+/// or `serialize_sol_record`
+pub fn create_record_v1_instructions(
+    domain: &str,
+    record: impl Into<RecordKey>,
+    data: Vec<u8>,
+    owner: &Pubkey,
+    payer: &Pubkey,
+) -> Result<Vec<Instruction>, SnsError> {
+    SnsConfig::DEFAULT.create_record_v1_instructions(domain, record, data, owner, payer)
+}
+
+/// Writes `data` in an existing record V1 currently holding `current_len` bytes, the account is
+/// reallocated when the size differs
+pub fn update_record_v1_instructions(
+    domain: &str,
+    record: impl Into<RecordKey>,
+    data: Vec<u8>,
+    current_len: usize,
+    owner: &Pubkey,
+    payer: &Pubkey,
+) -> Result<Vec<Instruction>, SnsError> {
+    SnsConfig::DEFAULT.update_record_v1_instructions(
+        domain,
+        record,
+        data,
+        current_len,
+        owner,
+        payer,
+    )
+}
+
+/// Deletes a record V1, the rent is sent to `refund_target`
+pub fn delete_record_v1_instruction(
+    domain: &str,
+    record: impl Into<RecordKey>,
+    owner: &Pubkey,
+    refund_target: &Pubkey,
+) -> Result<Instruction, SnsError> {
+    SnsConfig::DEFAULT.delete_record_v1_instruction(domain, record, owner, refund_target)
 }
 
-pub fn convert_u5_array(u5_data: &[u5]) -> Vec<u8> {
-    let mut u8_data: Vec<u8> = Vec::new();
-    let mut buffer: u16 = 0;
-    let mut buffer_length: u8 = 0;
-    for u5 in u5_data {
-        buffer = (buffer << 5) | (u5.to_u8() as u16);
-        buffer_length += 5;
-        while buffer_length >= 8 {
-            u8_data.push((buffer >> (buffer_length - 8)) as u8);
-            buffer_length -= 8;
+impl SnsConfig {
+    pub fn create_record_v1_instructions(
+        &self,
+        domain: &str,
+        record: impl Into<RecordKey>,
+        data: Vec<u8>,
+        owner: &Pubkey,
+        payer: &Pubkey,
+    ) -> Result<Vec<Instruction>, SnsError> {
+        let record = record.into();
+        let domain_key = self.get_domain_key(domain)?;
+        let key = self.get_record_key(domain, record.clone(), RecordVersion::V1)?;
+        let create = create(
+            spl_name_service::ID,
+            NameRegistryInstruction::Create {
+                hashed_name: get_hashed_name(
+                    &(get_prefix(Domain::Record(RecordVersion::V1)) + record.as_str()),
+                ),
+                lamports: get_record_v1_rent(data.len()),
+                space: data.len() as u32,
+            },
+            key,
+            *payer,
+            *owner,
+            None,
+            Some(domain_key),
+            Some(*owner),
+        )?;
+        let update = update(spl_name_service::ID, 0, data, key, *owner, None)?;
+        Ok(vec![create, update])
+    }
+
+    pub fn update_record_v1_instructions(
+        &self,
+        domain: &str,
+        record: impl Into<RecordKey>,
+        data: Vec<u8>,
+        current_len: usize,
+        owner: &Pubkey,
+        payer: &Pubkey,
+    ) -> Result<Vec<Instruction>, SnsError> {
+        let key = self.get_record_key(domain, record, RecordVersion::V1)?;
+        let mut ixs = vec![];
+        if current_len != data.len() {
+            ixs.push(realloc(
+                spl_name_service::ID,
+                *payer,
+                key,
+                *owner,
+                data.len() as u32,
+            )?);
         }
+        ixs.push(update(spl_name_service::ID, 0, data, key, *owner, None)?);
+        Ok(ixs)
     }
-    // Make sure there's no remaining data in the buffer
-    if buffer_length > 0 {
-        u8_data.push((buffer << (8 - buffer_length)) as u8);
+
+    pub fn delete_record_v1_instruction(
+        &self,
+        domain: &str,
+        record: impl Into<RecordKey>,
+        owner: &Pubkey,
+        refund_target: &Pubkey,
+    ) -> Result<Instruction, SnsError> {
+        let key = self.get_record_key(domain, record, RecordVersion::V1)?;
+        Ok(delete(spl_name_service::ID, key, *owner, *refund_target)?)
     }
-    u8_data
 }
 
 #[cfg(test)]
@@ -212,6 +300,89 @@ mod test {
         );
         let data = serialize_record("192.168.0.1", Record::A).unwrap();
         assert_eq!(data, [192, 168, 0, 1].to_vec());
+
+        // BASE records V1 are UTF-8 strings, as written by the JS SDK
+        let address = "0x4bfBFD1E018F9F27eeB788160579daF7E2cd7dA7";
+        let data = serialize_record(address, Record::BASE).unwrap();
+        assert_eq!(data, address.as_bytes());
+        let des = deserialize_record(&data, Record::BASE, &Pubkey::default(), &Pubkey::default());
+        assert_eq!(des.unwrap().to_string(), address);
+        let des = deserialize_record(
+            address.to_lowercase().as_bytes(),
+            Record::BASE,
+            &Pubkey::default(),
+            &Pubkey::default(),
+        );
+        assert_eq!(des.unwrap().to_string(), address);
+        assert!(serialize_record_value(
+            &RecordValue::parse(Record::A, "10.0.0.1").unwrap(),
+            Record::BASE
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_record_round_trip() {
+        let items = [
+            (
+                Record::Background,
+                "BNgXJbBULxqUaPXo2LsQkYzLPEHn1w2QCxoTYt8nvbG5",
+            ),
+            // Binary content ending with zeros
+            (Record::A, "10.0.0.0"),
+        ];
+        for (record, content) in items {
+            let data = serialize_record(content, record).unwrap();
+            assert_eq!(Some(data.len()), get_record_size(record));
+            let des = deserialize_record(&data, record, &Pubkey::default(), &Pubkey::default());
+            assert_eq!(des.unwrap().to_string(), content);
+        }
+
+        // Records without a SNS-IP definition are stored as UTF-8
+        for (record, content) in [
+            (
+                Record::Polygon,
+                "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            ),
+            (
+                Record::Ton,
+                "EQDKbjIcfM6ezt8KjKJJLshZJJSqX7XOA4ff-W72r5gqPrHF",
+            ),
+            (
+                Record::IPNS,
+                "k51qzi5uqu5dlvj2baxnqndepeb86cbk3ng7n3i46uzyxzyqj2xjonzllnv0v8",
+            ),
+        ] {
+            let data = serialize_record(content, record).unwrap();
+            assert_eq!(data, content.as_bytes());
+            let des = deserialize_record(&data, record, &Pubkey::default(), &Pubkey::default());
+            assert_eq!(des.unwrap(), RecordValue::Text(content.to_owned()));
+        }
+
+        // CNAME and TXT records are punycode encoded
+        for (record, content) in [
+            (Record::CNAME, "sns.id"),
+            (Record::CNAME, "bücher.example"),
+            (Record::TXT, "v=spf1 include:_spf.google.com ~all"),
+            (Record::TXT, "🇺🇸 domain"),
+        ] {
+            let data = serialize_record(content, record).unwrap();
+            assert_ne!(data, content.as_bytes());
+            let padded = [data.as_slice(), &[0; 8]].concat();
+            for data in [data, padded] {
+                let des = deserialize_record(&data, record, &Pubkey::default(), &Pubkey::default());
+                assert_eq!(des.unwrap(), RecordValue::Text(content.to_owned()));
+            }
+        }
+
+        // Old records are UTF-8 encoded, possibly with trailing zeros
+        let des = deserialize_record(
+            b"10.0.0.1\0\0",
+            Record::A,
+            &Pubkey::default(),
+            &Pubkey::default(),
+        );
+        assert_eq!(des.unwrap().to_string(), "10.0.0.1");
     }
 
     #[test]
@@ -223,4 +394,65 @@ mod test {
         let (_, data, _) = bech32::decode("inj1l3vt52kqzlvpaw2wfug45qkyncflq8hgr5nem7").unwrap();
         assert_eq!(expected, convert_u5_array(&data))
     }
+
+    #[test]
+    fn test_serialize_sol_record() {
+        use solana_sdk::signature::{Keypair, Signer};
+
+        let signer = Keypair::new();
+        let content = Pubkey::new_unique();
+        let record_key = crate::record::get_record_v1_key("bonfida", Record::Sol).unwrap();
+        let signature = signer.sign_message(&get_sol_record_message(&content, &record_key));
+
+        let data =
+            serialize_sol_record(&content, &record_key, &signer.pubkey(), signature.as_ref())
+                .unwrap();
+        assert_eq!(data.len(), get_record_size(Record::Sol).unwrap());
+        assert_eq!(&data[..32], content.as_ref());
+        assert!(matches!(
+            serialize_sol_record(&content, &content, &signer.pubkey(), signature.as_ref()),
+            Err(SnsError::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn test_verify_sol_record() {
+        use solana_sdk::signature::{Keypair, Signer};
+
+        let signer = Keypair::new();
+        let content = Pubkey::new_unique();
+        let record_key = Pubkey::new_unique();
+        let data = sign_sol_record(&content, &record_key, &signer).unwrap();
+        assert_eq!(
+            verify_sol_record(&data, &record_key, &signer.pubkey()).unwrap(),
+            Some(content)
+        );
+        assert_eq!(
+            verify_sol_record(&data, &record_key, &content).unwrap(),
+            None
+        );
+        assert_eq!(
+            verify_sol_record(&data, &Pubkey::new_unique(), &signer.pubkey()).unwrap(),
+            None
+        );
+        assert!(verify_sol_record(&data[..64], &record_key, &signer.pubkey()).is_err());
+
+        // The signed message is the hex encoding of the content and the record key, not their
+        // raw concatenation
+        let raw = [content.as_ref(), record_key.as_ref()].concat();
+        let raw_signature = signer.sign_message(&raw);
+        let raw_data = [content.as_ref(), raw_signature.as_ref()].concat();
+        assert_eq!(
+            verify_sol_record(&raw_data, &record_key, &signer.pubkey()).unwrap(),
+            None
+        );
+
+        let over_allocated = [data.as_slice(), &[0; 32]].concat();
+        assert_eq!(
+            deserialize_record(&over_allocated, Record::Sol, &record_key, &signer.pubkey())
+                .unwrap(),
+            RecordValue::Pubkey(content)
+        );
+        assert!(deserialize_record(&raw_data, Record::Sol, &record_key, &signer.pubkey()).is_err());
+    }
 }
@@ -1,264 +1,705 @@
 use borsh::BorshDeserialize;
+use sns_records::instruction::{
+    allocate_and_post_record, delete_record, edit_record, validate_ethereum_signature,
+    validate_solana_signature, write_roa,
+};
 use sns_records::state::{
     record_header::RecordHeader,
     validation::{get_validation_length, Validation},
 };
-use solana_program::{program_pack::Pack, pubkey};
+use solana_program::{
+    instruction::Instruction, keccak, program_pack::Pack, pubkey,
+    secp256k1_recover::secp256k1_recover, system_program,
+};
+use std::borrow::Cow;
 
-use super::{convert_u5_array, get_record_key, Record};
+use super::{
+    validate::validate_record_content, value::RecordValue, Record, RecordKey, RecordStatus,
+    RecordVersion, CENTRAL_STATE_RECORD_V2,
+};
 use crate::{
+    config::SnsConfig,
+    derivation::{get_prefix, Domain},
     error::SnsError,
+    fetcher::AccountFetcher,
     non_blocking::resolve::{resolve_name_registry, resolve_name_registry_batch},
 };
-use {
-    bech32::ToBase32,
-    solana_client::nonblocking::rpc_client::RpcClient,
-    solana_program::pubkey::Pubkey,
-    spl_name_service::state::NameRecordHeader,
-    std::net::{Ipv4Addr, Ipv6Addr},
-    std::str::FromStr,
-};
+use {solana_program::pubkey::Pubkey, spl_name_service::state::NameRecordHeader};
 
 pub struct ParsedRecord<'a> {
     pub kind: Record,
     pub header: RecordHeader,
     pub roa_id: &'a [u8],
     pub staleness_id: &'a [u8],
-    pub content: String,
+    pub content: RecordValue,
 }
 
 pub const GUARDIAN_ID: Pubkey = pubkey!("ExXjtfdQe8JacoqP9Z535WzQKjF4CzW1TTRKRgpxvya3");
 
+/// The guardians trusted to validate the right of association of records, the other records
+/// validate it with their content
+///
+/// No devnet guardian is published, so `SnsConfig::DEVNET` trusts these mainnet guardians as
+/// well. Callers running their own guardian can set `SnsConfig::guardians`.
+pub const GUARDIANS: [(Record, Cow<'static, [Pubkey]>); 2] = [
+    (Record::Url, Cow::Borrowed(&[GUARDIAN_ID])),
+    (Record::CNAME, Cow::Borrowed(&[GUARDIAN_ID])),
+];
+
 impl<'a> ParsedRecord<'a> {
-    pub fn verify_staleness(
-        &self,
-        domain_owner_key: Pubkey,
-        domain_owner_account_data: Option<&[u8]>,
-    ) -> Result<(), SnsError> {
-        if domain_owner_key == sns_warp_common::constants::EMITTER_KEY {
-            // The domain is XChain-owned
-            if self.header.staleness_validation != Validation::XChain as u16 {
-                return Err(SnsError::StaleRecord);
-            }
-            let domain_owner_account_data =
-                domain_owner_account_data.ok_or(SnsError::StaleRecord)?;
-            let xchain_record = sns_warp_common::state::x_domain::XDomain::try_from_slice(
-                domain_owner_account_data,
-            )?;
-            let expected_owner_chain = u16::from_le_bytes(
-                self.staleness_id
-                    .get(..2)
-                    .ok_or(SnsError::InvalidRecordData)?
-                    .try_into()
-                    .unwrap(),
-            );
-            let expected_owner_address = self
-                .staleness_id
-                .get(2..)
-                .ok_or(SnsError::InvalidRecordData)?;
-            if expected_owner_chain != xchain_record.owner_chain
-                || expected_owner_address != xchain_record.owner_address
-            {
-                return Err(SnsError::StaleRecord);
-            }
-        }
-        if self.header.staleness_validation != Validation::Solana as u16
-            || self.staleness_id != domain_owner_key.as_ref()
-        {
+    /// Checks that the record was signed by the current owner of the domain, see
+    /// `SnsConfig::get_domain_owner_id`
+    pub fn verify_staleness(&self, domain_owner: &ValidationId) -> Result<(), SnsError> {
+        let validation = Validation::try_from(self.header.staleness_validation)?;
+        let valid = ValidationId::parse(validation, self.staleness_id)
+            .is_ok_and(|record_owner| is_signed_by(validation, &record_owner, domain_owner));
+        if !valid {
             return Err(SnsError::StaleRecord);
         }
         Ok(())
     }
 
-    pub fn verify_roa(&self) -> Result<(), SnsError> {
+    /// Checks that the right of association was validated by one of the trusted `guardians` of
+    /// the record, see `SnsConfig::get_guardians`, or by the content itself (e.g the EVM address
+    /// of an `Eth` record) when there are none
+    ///
+    /// Returns the guardian which validated the right of association, if any.
+    pub fn verify_roa(&self, guardians: &[Pubkey]) -> Result<Option<Pubkey>, SnsError> {
         let validation = self.kind.roa_validation();
         if validation as u16 != self.header.right_of_association_validation {
             return Err(SnsError::UnverifiedRecord);
         }
-        if matches!(self.kind, Record::CNAME | Record::Url) && self.roa_id != GUARDIAN_ID.as_ref() {
-            return Err(SnsError::UnverifiedRecord);
+        let roa_id = ValidationId::parse(validation, self.roa_id)?;
+        if !guardians.is_empty() {
+            return find_guardian(guardians, &roa_id)
+                .map(Some)
+                .ok_or(SnsError::UnverifiedRecord);
+        }
+        // Content which does not hold a key (e.g a `Url` without guardians) cannot validate it
+        let content = self.content.to_bytes(self.kind)?;
+        match ValidationId::parse(validation, &content) {
+            Ok(id) if id == roa_id => Ok(None),
+            _ => Err(SnsError::UnverifiedRecord),
         }
-        Ok(())
+    }
+
+    /// Reports the validations of the record against its trusted `guardians` and the current
+    /// owner of the domain, see `verify_roa` and `verify_staleness` for the arguments
+    pub fn verification(
+        &self,
+        guardians: &[Pubkey],
+        domain_owner: ValidationId,
+    ) -> Result<RecordVerification, SnsError> {
+        let content = self.content.to_bytes(self.kind)?;
+        let raw = RawRecordV2 {
+            header: self.header,
+            staleness_id: self.staleness_id,
+            roa_id: self.roa_id,
+            content: &content,
+        };
+        raw.verification(self.kind, guardians, domain_owner)
     }
 }
 
-pub async fn parse_record_v2(
-    record: Record,
-    account_data: &[u8],
-) -> Result<ParsedRecord, SnsError> {
-    let record_header = RecordHeader::from_buffer(account_data);
+fn find_guardian(guardians: &[Pubkey], roa_id: &ValidationId) -> Option<Pubkey> {
+    guardians
+        .iter()
+        .find(|guardian| *roa_id == ValidationId::Solana(**guardian))
+        .copied()
+}
+
+/// The identity held by a validation ID of a record V2
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ValidationId {
+    None,
+    /// A Solana key, for the `Solana` and `UnverifiedSolana` validations
+    Solana(Pubkey),
+    Ethereum([u8; 20]),
+    /// An address on another chain, in the Wormhole format
+    XChain {
+        chain: u16,
+        address: [u8; 32],
+    },
+}
+
+impl ValidationId {
+    pub fn parse(validation: Validation, id: &[u8]) -> Result<Self, SnsError> {
+        let id = match validation {
+            Validation::None => Self::None,
+            Validation::Solana | Validation::UnverifiedSolana => {
+                Self::Solana(Pubkey::new_from_array(id.try_into()?))
+            }
+            Validation::Ethereum => Self::Ethereum(id.try_into()?),
+            Validation::XChain => {
+                let [c0, c1, ref address @ ..] = id[..] else {
+                    return Err(SnsError::InvalidRecordData);
+                };
+                Self::XChain {
+                    chain: u16::from_le_bytes([c0, c1]),
+                    address: address.try_into()?,
+                }
+            }
+        };
+        Ok(id)
+    }
+}
+
+/// The validations of a record V2, which explain why it is trusted or not
+#[derive(Clone, Debug)]
+pub struct RecordVerification {
+    pub staleness_validation: Validation,
+    /// The owner which signed the record, from the staleness ID
+    pub record_owner: ValidationId,
+    /// The current owner of the domain
+    pub domain_owner: ValidationId,
+    pub roa_validation: Validation,
+    /// The right of association validation required by the record
+    pub expected_roa_validation: Validation,
+    /// The key which validated the right of association, from the right of association ID
+    pub roa_signer: ValidationId,
+    /// The key held by the content, which has to validate the right of association when the
+    /// record has no guardians
+    pub expected_roa_signer: ValidationId,
+    /// The guardians trusted to validate the right of association
+    pub guardians: Vec<Pubkey>,
+    /// The trusted guardian which validated the right of association
+    pub roa_guardian: Option<Pubkey>,
+    pub content_length: u32,
+}
+
+impl RecordVerification {
+    /// Whether the record was not signed by the current owner of the domain
+    pub fn is_stale(&self) -> bool {
+        !is_signed_by(
+            self.staleness_validation,
+            &self.record_owner,
+            &self.domain_owner,
+        )
+    }
+
+    pub fn is_roa_verified(&self) -> bool {
+        self.roa_validation as u16 == self.expected_roa_validation as u16
+            && if self.guardians.is_empty() {
+                self.roa_signer == self.expected_roa_signer
+            } else {
+                self.roa_guardian.is_some()
+            }
+    }
+
+    pub fn status(&self) -> RecordStatus {
+        if self.is_stale() {
+            RecordStatus::Stale
+        } else if !self.is_roa_verified() {
+            RecordStatus::UnverifiedRoa
+        } else {
+            RecordStatus::Verified
+        }
+    }
+}
+
+/// Whether the staleness validation of a record proves that it was signed by `domain_owner`
+fn is_signed_by(
+    validation: Validation,
+    record_owner: &ValidationId,
+    domain_owner: &ValidationId,
+) -> bool {
+    // Neither Ethereum nor UnverifiedSolana prove that the current owner signed the record
+    matches!(validation, Validation::Solana | Validation::XChain) && record_owner == domain_owner
+}
+
+/// The sections of a record V2 account
+pub struct RawRecordV2<'a> {
+    pub header: RecordHeader,
+    pub staleness_id: &'a [u8],
+    pub roa_id: &'a [u8],
+    /// The serialized content of the record
+    pub content: &'a [u8],
+}
+
+impl RawRecordV2<'_> {
+    /// Reports the validations of the record, without deserializing its content
+    pub fn verification(
+        &self,
+        record: Record,
+        guardians: &[Pubkey],
+        domain_owner: ValidationId,
+    ) -> Result<RecordVerification, SnsError> {
+        let staleness_validation = Validation::try_from(self.header.staleness_validation)?;
+        let roa_validation = Validation::try_from(self.header.right_of_association_validation)?;
+        let roa_signer = ValidationId::parse(roa_validation, self.roa_id)?;
+        Ok(RecordVerification {
+            staleness_validation,
+            record_owner: ValidationId::parse(staleness_validation, self.staleness_id)?,
+            domain_owner,
+            roa_validation,
+            expected_roa_validation: record.roa_validation(),
+            // Content which does not hold a key cannot validate its right of association
+            expected_roa_signer: ValidationId::parse(record.roa_validation(), self.content)
+                .unwrap_or(ValidationId::None),
+            roa_guardian: find_guardian(guardians, &roa_signer),
+            roa_signer,
+            guardians: guardians.to_vec(),
+            content_length: self.header.content_length,
+        })
+    }
+}
+
+pub fn split_record_v2(account_data: &[u8]) -> Result<RawRecordV2<'_>, SnsError> {
+    if account_data.len() < NameRecordHeader::LEN + RecordHeader::LEN {
+        return Err(SnsError::InvalidRecordData);
+    }
+    let header = RecordHeader::from_buffer(account_data);
     let mut offset = spl_name_service::state::NameRecordHeader::LEN + RecordHeader::LEN;
-    let roa_validation = Validation::try_from(record_header.right_of_association_validation)?;
-    let staleness_validation = Validation::try_from(record_header.staleness_validation)?;
-    let mut length = get_validation_length(roa_validation) as usize;
-    let roa_id = account_data
+    let roa_validation = Validation::try_from(header.right_of_association_validation)?;
+    let staleness_validation = Validation::try_from(header.staleness_validation)?;
+    // The staleness ID is stored before the right of association ID
+    let mut length = get_validation_length(staleness_validation) as usize;
+    let staleness_id = account_data
         .get(offset..offset + length)
         .ok_or(SnsError::InvalidRecordData)?;
     offset += length;
-    length = get_validation_length(staleness_validation) as usize;
-    let staleness_id = account_data
+    length = get_validation_length(roa_validation) as usize;
+    let roa_id = account_data
         .get(offset..offset + length)
         .ok_or(SnsError::InvalidRecordData)?;
     offset += length;
-    let content = deserialize_record_v2_content(
-        account_data
-            .get(offset..)
-            .ok_or(SnsError::InvalidRecordData)?,
-        record,
-    )?;
-    Ok(ParsedRecord {
-        kind: record,
-        header: record_header,
-        roa_id,
+    let content = account_data
+        .get(offset..)
+        .ok_or(SnsError::InvalidRecordData)?;
+    Ok(RawRecordV2 {
+        header,
         staleness_id,
+        roa_id,
         content,
     })
 }
 
+pub async fn parse_record_v2(
+    record: Record,
+    account_data: &[u8],
+) -> Result<ParsedRecord<'_>, SnsError> {
+    let raw = split_record_v2(account_data)?;
+    Ok(ParsedRecord {
+        kind: record,
+        header: raw.header,
+        roa_id: raw.roa_id,
+        staleness_id: raw.staleness_id,
+        content: deserialize_record_v2_content(raw.content, record)?,
+    })
+}
+
 pub async fn retrieve_record_v2(
-    rpc_client: RpcClient,
+    fetcher: &(impl AccountFetcher + ?Sized),
     record: Record,
     domain: &str,
 ) -> Result<Option<(NameRecordHeader, Vec<u8>)>, SnsError> {
-    let record_key = get_record_key(domain, record, super::RecordVersion::V2)?;
-    resolve_name_registry(&rpc_client, &record_key).await
+    let record_key = fetcher
+        .config()
+        .get_record_key(domain, record, super::RecordVersion::V2)?;
+    resolve_name_registry(fetcher, &record_key).await
 }
 
 pub async fn retrieve_records_batch_v2(
-    rpc_client: RpcClient,
+    fetcher: &(impl AccountFetcher + ?Sized),
     records: &[Record],
     domain: &str,
 ) -> Result<Vec<Option<(NameRecordHeader, Vec<u8>)>>, SnsError> {
     let pubkeys: Vec<Pubkey> = records
         .iter()
-        .map(|r| get_record_key(domain, *r, super::RecordVersion::V2))
+        .map(|r| {
+            fetcher
+                .config()
+                .get_record_key(domain, *r, super::RecordVersion::V2)
+        })
         .collect::<Result<Vec<_>, _>>()?;
-    resolve_name_registry_batch(&rpc_client, &pubkeys).await
+    resolve_name_registry_batch(fetcher, &pubkeys).await
 }
 
-pub fn deserialize_record_v2_content(content: &[u8], record: Record) -> Result<String, SnsError> {
-    match record {
-        // UTF-8 encoded record
-        Record::Ipfs
-        | Record::Arwv
-        | Record::Ltc
-        | Record::Doge
-        | Record::Email
-        | Record::Url
-        | Record::Discord
-        | Record::Github
-        | Record::Reddit
-        | Record::Twitter
-        | Record::Telegram
-        | Record::Pic
-        | Record::Shdw
-        | Record::Point
-        | Record::Backpack
-        | Record::TXT
-        | Record::Btc
-        | Record::CNAME => {
-            let decoded = String::from_utf8(content.to_vec())?;
-            if matches!(record, Record::CNAME | Record::TXT) {
-                let decoded = punycode::decode(&decoded).map_err(|_| SnsError::Punycode)?;
-                Ok(decoded)
-            } else {
-                Ok(decoded)
-            }
-        }
-        Record::Sol => {
-            let bytes: [u8; 32] = content.try_into()?;
-            let pubkey = Pubkey::new_from_array(bytes);
-            Ok(pubkey.to_string())
-        }
-        Record::Injective => {
-            let des = bech32::encode("inj", content.to_base32(), bech32::Variant::Bech32)?;
-            Ok(des)
-        }
-        Record::Bsc | Record::Eth | Record::BASE => {
-            let des = format!("0x{}", hex::encode(content));
-            Ok(des)
-        }
-        Record::AAAA => {
-            let bytes: [u8; 16] = content.try_into()?;
-            let ip = Ipv6Addr::from(bytes);
-            Ok(ip.to_string())
-        }
-        Record::A => {
-            let bytes: [u8; 4] = content.try_into()?;
-            let ip = Ipv4Addr::from(bytes);
-            Ok(ip.to_string())
-        }
-    }
+pub fn deserialize_record_v2_content(
+    content: &[u8],
+    record: Record,
+) -> Result<RecordValue, SnsError> {
+    RecordValue::from_bytes(record, content)
 }
 
+/// Serializes the content of a record V2, textual content is checked with
+/// `validate_record_content`
 pub fn serialize_record_v2_content(content: &str, record: Record) -> Result<Vec<u8>, SnsError> {
-    match record {
-        // UTF-8 encoded record
-        Record::Ipfs
-        | Record::Arwv
-        | Record::Ltc
-        | Record::Doge
-        | Record::Email
-        | Record::Url
-        | Record::Discord
-        | Record::Github
-        | Record::Reddit
-        | Record::Twitter
-        | Record::Telegram
-        | Record::Pic
-        | Record::Shdw
-        | Record::Point
-        | Record::Backpack
-        | Record::TXT
-        | Record::Btc
-        | Record::CNAME => {
-            if matches!(record, Record::CNAME | Record::TXT) {
-                let encoded = punycode::encode(content).map_err(|_| SnsError::Punycode)?;
-                Ok(encoded.as_bytes().to_vec())
-            } else {
-                Ok(content.as_bytes().to_vec())
-            }
-        }
-        Record::Sol => {
-            let pubkey = Pubkey::from_str(content).map_err(|_| SnsError::InvalidPubkey)?;
-            Ok(pubkey.to_bytes().to_vec())
-        }
-        Record::Injective => {
-            if !content.starts_with("inj") {
-                return Err(SnsError::InvalidInjectiveAddress);
-            }
-            let (_, data, _) = bech32::decode(content)?;
-            let data = convert_u5_array(&data);
-            if data.len() != 20 {
-                return Err(SnsError::InvalidInjectiveAddress);
-            }
-            Ok(data)
-        }
-        Record::Bsc | Record::Eth | Record::BASE => {
-            if !content.starts_with("0x") {
-                return Err(SnsError::InvalidEvmAddress);
-            }
-            let decoded = hex::decode(content.get(2..).ok_or(SnsError::InvalidEvmAddress)?)?;
-            if decoded.len() != 20 {
-                return Err(SnsError::InvalidEvmAddress);
-            }
-            Ok(decoded)
-        }
-        Record::AAAA => {
-            let ip = content
-                .parse::<Ipv6Addr>()
-                .map_err(|_| SnsError::InvalidIpv6)?;
-            Ok(ip.octets().to_vec())
-        }
-        Record::A => {
-            let ip = content
-                .parse::<Ipv4Addr>()
-                .map_err(|_| SnsError::InvalidIpv4)?;
-            Ok(ip.octets().to_vec())
+    validate_record_content(record, content)?;
+    serialize_record_v2_content_unchecked(content, record)
+}
+
+/// Serializes the content of a record V2 without validating textual content, the result can be
+/// written with `create_raw_record_v2_instruction`
+pub fn serialize_record_v2_content_unchecked(
+    content: &str,
+    record: Record,
+) -> Result<Vec<u8>, SnsError> {
+    RecordValue::parse(record, content)?.to_bytes(record)
+}
+
+/// Creates a record V2 holding `content`, serialized following SNS-IP 1
+pub fn create_record_v2_instruction(
+    domain: &str,
+    record: Record,
+    content: &str,
+    owner: &Pubkey,
+    payer: &Pubkey,
+) -> Result<Instruction, SnsError> {
+    SnsConfig::DEFAULT.create_record_v2_instruction(domain, record, content, owner, payer)
+}
+
+/// Replaces the content of an existing record V2, which resets its validations
+pub fn update_record_v2_instruction(
+    domain: &str,
+    record: Record,
+    content: &str,
+    owner: &Pubkey,
+    payer: &Pubkey,
+) -> Result<Instruction, SnsError> {
+    SnsConfig::DEFAULT.update_record_v2_instruction(domain, record, content, owner, payer)
+}
+
+/// Creates a record V2 holding raw `content`, e.g. a custom record
+pub fn create_raw_record_v2_instruction(
+    domain: &str,
+    record: impl Into<RecordKey>,
+    content: Vec<u8>,
+    owner: &Pubkey,
+    payer: &Pubkey,
+) -> Result<Instruction, SnsError> {
+    SnsConfig::DEFAULT.create_raw_record_v2_instruction(domain, record, content, owner, payer)
+}
+
+pub fn update_raw_record_v2_instruction(
+    domain: &str,
+    record: impl Into<RecordKey>,
+    content: Vec<u8>,
+    owner: &Pubkey,
+    payer: &Pubkey,
+) -> Result<Instruction, SnsError> {
+    SnsConfig::DEFAULT.update_raw_record_v2_instruction(domain, record, content, owner, payer)
+}
+
+pub fn delete_record_v2_instruction(
+    domain: &str,
+    record: impl Into<RecordKey>,
+    owner: &Pubkey,
+    payer: &Pubkey,
+) -> Result<Instruction, SnsError> {
+    SnsConfig::DEFAULT.delete_record_v2_instruction(domain, record, owner, payer)
+}
+
+/// Validates the staleness (or the right of association when `staleness` is false) of a record
+/// V2 with the signature of `verifier`
+pub fn validate_record_v2_content(
+    staleness: bool,
+    domain: &str,
+    record: impl Into<RecordKey>,
+    owner: &Pubkey,
+    payer: &Pubkey,
+    verifier: &Pubkey,
+) -> Result<Instruction, SnsError> {
+    SnsConfig::DEFAULT.validate_record_v2_content(staleness, domain, record, owner, payer, verifier)
+}
+
+/// The EIP-191 message an Ethereum address signs to validate the right of association of the
+/// record V2 `record_key`
+///
+/// `content` is the serialized content of the record and `staleness_id` the owner which
+/// validated its staleness.
+pub fn get_eth_roa_message(content: &[u8], record_key: &Pubkey, staleness_id: &Pubkey) -> Vec<u8> {
+    let message = format!(
+        "{}\nFor record: {record_key}\nStaleness ID: {staleness_id}",
+        hex::encode(content)
+    );
+    format!("\x19Ethereum Signed Message:\n{}{message}", message.len()).into_bytes()
+}
+
+/// Recovers the Ethereum address which signed `message`, `signature` being the 65 bytes
+/// `r || s || v` with `v` either 27 or 28
+pub fn recover_eth_address(message: &[u8], signature: &[u8]) -> Result<[u8; 20], SnsError> {
+    let [ref rs @ .., v] = signature[..] else {
+        return Err(SnsError::InvalidSignature);
+    };
+    let recovery_id = v.checked_sub(27).ok_or(SnsError::InvalidSignature)?;
+    if rs.len() != 64 || recovery_id > 1 {
+        return Err(SnsError::InvalidSignature);
+    }
+    let pubkey = secp256k1_recover(keccak::hash(message).as_ref(), recovery_id, rs)
+        .map_err(|_| SnsError::InvalidSignature)?;
+    Ok(keccak::hash(&pubkey.0).to_bytes()[12..].try_into().unwrap())
+}
+
+/// Checks offline that `signature` validates the right of association of the record V2
+/// `record_key`, i.e that it was produced by the EVM address held in `content`
+///
+/// The records program performs the same check in `eth_validate_record_v2_content`.
+pub fn verify_eth_roa_signature(
+    content: &[u8],
+    record_key: &Pubkey,
+    staleness_id: &Pubkey,
+    signature: &[u8],
+) -> Result<(), SnsError> {
+    let message = get_eth_roa_message(content, record_key, staleness_id);
+    if recover_eth_address(&message, signature)? != content {
+        return Err(SnsError::InvalidSignature);
+    }
+    Ok(())
+}
+
+/// Validates the right of association of a record V2 with the signature of an Ethereum address,
+/// see `get_eth_roa_message`
+pub fn eth_validate_record_v2_content(
+    domain: &str,
+    record: impl Into<RecordKey>,
+    owner: &Pubkey,
+    payer: &Pubkey,
+    signature: Vec<u8>,
+    expected_pubkey: Vec<u8>,
+) -> Result<Instruction, SnsError> {
+    SnsConfig::DEFAULT.eth_validate_record_v2_content(
+        domain,
+        record,
+        owner,
+        payer,
+        signature,
+        expected_pubkey,
+    )
+}
+
+/// Writes the key expected to validate the right of association of a record V2
+pub fn write_roa_record_v2(
+    domain: &str,
+    record: impl Into<RecordKey>,
+    owner: &Pubkey,
+    payer: &Pubkey,
+    roa_id: &Pubkey,
+) -> Result<Instruction, SnsError> {
+    SnsConfig::DEFAULT.write_roa_record_v2(domain, record, owner, payer, roa_id)
+}
+
+impl SnsConfig {
+    /// The identity of the current owner of a domain, which has to sign its records V2
+    ///
+    /// `domain_owner_key` is the owner of the name registry, or the NFT holder for tokenized
+    /// domains. When it is the `warp_emitter`, the domain is XChain-owned and its owner is read
+    /// from `x_domain_data`, the data of its `XDomain` account. The owner is `ValidationId::None`
+    /// when that data is missing, so that all the records of the domain are stale.
+    pub fn get_domain_owner_id(
+        &self,
+        domain_owner_key: Pubkey,
+        x_domain_data: Option<&[u8]>,
+    ) -> Result<ValidationId, SnsError> {
+        if domain_owner_key != self.warp_emitter {
+            return Ok(ValidationId::Solana(domain_owner_key));
         }
+        let Some(data) = x_domain_data else {
+            return Ok(ValidationId::None);
+        };
+        let x_domain = sns_warp_common::state::x_domain::XDomain::try_from_slice(data)?;
+        Ok(ValidationId::XChain {
+            chain: x_domain.owner_chain,
+            address: x_domain.owner_address,
+        })
+    }
+
+    pub fn create_record_v2_instruction(
+        &self,
+        domain: &str,
+        record: Record,
+        content: &str,
+        owner: &Pubkey,
+        payer: &Pubkey,
+    ) -> Result<Instruction, SnsError> {
+        let content = serialize_record_v2_content(content, record)?;
+        self.create_raw_record_v2_instruction(domain, record, content, owner, payer)
+    }
+
+    pub fn create_raw_record_v2_instruction(
+        &self,
+        domain: &str,
+        record: impl Into<RecordKey>,
+        content: Vec<u8>,
+        owner: &Pubkey,
+        payer: &Pubkey,
+    ) -> Result<Instruction, SnsError> {
+        let record = record.into();
+        let (record_key, domain_key) = self.record_v2_keys(domain, &record)?;
+        Ok(allocate_and_post_record(
+            allocate_and_post_record::Accounts {
+                system_program: &system_program::ID,
+                spl_name_service_program: &spl_name_service::ID,
+                fee_payer: payer,
+                record: &record_key,
+                domain: &domain_key,
+                domain_owner: owner,
+                central_state: &CENTRAL_STATE_RECORD_V2,
+            },
+            allocate_and_post_record::Params {
+                record: record_v2_name(&record),
+                content,
+            },
+        ))
+    }
+
+    pub fn update_record_v2_instruction(
+        &self,
+        domain: &str,
+        record: Record,
+        content: &str,
+        owner: &Pubkey,
+        payer: &Pubkey,
+    ) -> Result<Instruction, SnsError> {
+        let content = serialize_record_v2_content(content, record)?;
+        self.update_raw_record_v2_instruction(domain, record, content, owner, payer)
+    }
+
+    pub fn update_raw_record_v2_instruction(
+        &self,
+        domain: &str,
+        record: impl Into<RecordKey>,
+        content: Vec<u8>,
+        owner: &Pubkey,
+        payer: &Pubkey,
+    ) -> Result<Instruction, SnsError> {
+        let record = record.into();
+        let (record_key, domain_key) = self.record_v2_keys(domain, &record)?;
+        Ok(edit_record(
+            edit_record::Accounts {
+                system_program: &system_program::ID,
+                spl_name_service_program: &spl_name_service::ID,
+                fee_payer: payer,
+                record: &record_key,
+                domain: &domain_key,
+                domain_owner: owner,
+                central_state: &CENTRAL_STATE_RECORD_V2,
+            },
+            edit_record::Params {
+                record: record_v2_name(&record),
+                content,
+            },
+        ))
+    }
+
+    pub fn delete_record_v2_instruction(
+        &self,
+        domain: &str,
+        record: impl Into<RecordKey>,
+        owner: &Pubkey,
+        payer: &Pubkey,
+    ) -> Result<Instruction, SnsError> {
+        let (record_key, domain_key) = self.record_v2_keys(domain, &record.into())?;
+        Ok(delete_record(
+            delete_record::Accounts {
+                system_program: &system_program::ID,
+                spl_name_service_program: &spl_name_service::ID,
+                fee_payer: payer,
+                record: &record_key,
+                domain: &domain_key,
+                domain_owner: owner,
+                central_state: &CENTRAL_STATE_RECORD_V2,
+            },
+            delete_record::Params {},
+        ))
+    }
+
+    pub fn validate_record_v2_content(
+        &self,
+        staleness: bool,
+        domain: &str,
+        record: impl Into<RecordKey>,
+        owner: &Pubkey,
+        payer: &Pubkey,
+        verifier: &Pubkey,
+    ) -> Result<Instruction, SnsError> {
+        let (record_key, domain_key) = self.record_v2_keys(domain, &record.into())?;
+        Ok(validate_solana_signature(
+            validate_solana_signature::Accounts {
+                system_program: &system_program::ID,
+                spl_name_service_program: &spl_name_service::ID,
+                fee_payer: payer,
+                record: &record_key,
+                domain: &domain_key,
+                domain_owner: owner,
+                central_state: &CENTRAL_STATE_RECORD_V2,
+                verifier,
+            },
+            validate_solana_signature::Params { staleness },
+        ))
+    }
+
+    pub fn eth_validate_record_v2_content(
+        &self,
+        domain: &str,
+        record: impl Into<RecordKey>,
+        owner: &Pubkey,
+        payer: &Pubkey,
+        signature: Vec<u8>,
+        expected_pubkey: Vec<u8>,
+    ) -> Result<Instruction, SnsError> {
+        let (record_key, domain_key) = self.record_v2_keys(domain, &record.into())?;
+        Ok(validate_ethereum_signature(
+            validate_ethereum_signature::Accounts {
+                system_program: &system_program::ID,
+                spl_name_service_program: &spl_name_service::ID,
+                fee_payer: payer,
+                record: &record_key,
+                domain: &domain_key,
+                domain_owner: owner,
+                central_state: &CENTRAL_STATE_RECORD_V2,
+            },
+            validate_ethereum_signature::Params {
+                validation: Validation::Ethereum,
+                signature,
+                expected_pubkey,
+            },
+        ))
+    }
+
+    pub fn write_roa_record_v2(
+        &self,
+        domain: &str,
+        record: impl Into<RecordKey>,
+        owner: &Pubkey,
+        payer: &Pubkey,
+        roa_id: &Pubkey,
+    ) -> Result<Instruction, SnsError> {
+        let (record_key, domain_key) = self.record_v2_keys(domain, &record.into())?;
+        Ok(write_roa(
+            write_roa::Accounts {
+                system_program: &system_program::ID,
+                spl_name_service_program: &spl_name_service::ID,
+                fee_payer: payer,
+                record: &record_key,
+                domain: &domain_key,
+                domain_owner: owner,
+                central_state: &CENTRAL_STATE_RECORD_V2,
+            },
+            write_roa::Params {
+                roa_id: roa_id.to_bytes().to_vec(),
+            },
+        ))
+    }
+
+    // The record V2 key and the key of the domain it belongs to
+    fn record_v2_keys(
+        &self,
+        domain: &str,
+        record: &RecordKey,
+    ) -> Result<(Pubkey, Pubkey), SnsError> {
+        Ok((
+            self.get_record_key(domain, record.clone(), RecordVersion::V2)?,
+            self.get_domain_key(domain)?,
+        ))
     }
 }
 
+// The name hashed by the records program to derive the record key
+fn record_v2_name(record: &RecordKey) -> String {
+    get_prefix(Domain::Record(RecordVersion::V2)) + record.as_str()
+}
+
 #[cfg(test)]
 mod test {
 
@@ -288,7 +729,7 @@ mod test {
             116, 104, 105, 115, 32, 105, 115, 32, 97, 32, 116, 101, 115, 116, 45,
         ];
         let des = deserialize_record_v2_content(&buffer, Record::TXT).unwrap();
-        assert_eq!(des, content);
+        assert_eq!(des.to_string(), content);
 
         let content = "D8mRVSXrE2uU8KDAKQsGbfBNRyunMrmHBdEMrtWz1cUc";
         let buffer = vec![
@@ -296,7 +737,7 @@ mod test {
             64, 210, 198, 161, 135, 12, 123, 255, 155, 246, 126, 213, 29,
         ];
         let des = deserialize_record_v2_content(&buffer, Record::Sol).unwrap();
-        assert_eq!(des, content)
+        assert_eq!(des.to_string(), content)
     }
 
     #[test]
@@ -304,11 +745,335 @@ mod test {
         let content = "test";
         let ser = serialize_record_v2_content(content, Record::TXT).unwrap();
         let des = deserialize_record_v2_content(&ser, Record::TXT).unwrap();
-        assert_eq!(content, des);
+        assert_eq!(content, des.to_string());
 
         let content = "192.168.0.0";
         let ser = serialize_record_v2_content(content, Record::A).unwrap();
         let des = deserialize_record_v2_content(&ser, Record::A).unwrap();
-        assert_eq!(content, des);
+        assert_eq!(content, des.to_string());
+    }
+
+    #[tokio::test]
+    async fn test_retrieve_record_v2() {
+        use crate::test_utils::{LedgerBuilder, MockRecordV2};
+
+        let owner = Pubkey::new_unique();
+        let record = MockRecordV2::new(Record::Url, "https://sns.id")
+            .staleness(&owner)
+            .roa(Validation::Solana, owner.as_ref());
+        let fetcher = LedgerBuilder::new()
+            .domain("bonfida", owner)
+            .record_v2("bonfida", Record::Url, record)
+            .build();
+
+        let (header, _) = retrieve_record_v2(&fetcher, Record::Url, "bonfida")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(header.owner, owner);
+        assert!(retrieve_record_v2(&fetcher, Record::TXT, "bonfida")
+            .await
+            .unwrap()
+            .is_none());
+
+        let account = fetcher
+            .get(&crate::record::get_record_v2_key("bonfida", Record::Url).unwrap())
+            .unwrap();
+        let parsed = parse_record_v2(Record::Url, &account.data).await.unwrap();
+        assert_eq!(parsed.content.to_string(), "https://sns.id");
+        assert!(parsed
+            .verify_staleness(&ValidationId::Solana(owner))
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_parse_record_v2_ids() {
+        use crate::test_utils::MockRecordV2;
+
+        let owner = Pubkey::new_unique();
+        let address = "0x4bfBFD1E018F9F27eeB788160579daF7E2cd7dA7";
+        let eth = hex::decode(&address[2..]).unwrap();
+        let record = MockRecordV2::new(Record::Eth, address)
+            .staleness(&owner)
+            .roa(Validation::Ethereum, &eth);
+        let data = [vec![0; NameRecordHeader::LEN], record.serialize()].concat();
+
+        let parsed = parse_record_v2(Record::Eth, &data).await.unwrap();
+        assert_eq!(parsed.staleness_id, owner.as_ref());
+        assert_eq!(parsed.roa_id, eth);
+        assert_eq!(parsed.content.to_string(), address);
+        assert!(parse_record_v2(Record::Eth, &data[..100]).await.is_err());
+    }
+
+    #[test]
+    fn test_eth_roa_signature() {
+        let secret = libsecp256k1::SecretKey::parse(&[42; 32]).unwrap();
+        let pubkey = libsecp256k1::PublicKey::from_secret_key(&secret);
+        let address = &keccak::hash(&pubkey.serialize()[1..]).to_bytes()[12..];
+        let record_key = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+
+        let message = get_eth_roa_message(address, &record_key, &owner);
+        let text = format!(
+            "{}\nFor record: {record_key}\nStaleness ID: {owner}",
+            hex::encode(address)
+        );
+        assert_eq!(
+            message,
+            [
+                b"\x19Ethereum Signed Message:\n".as_slice(),
+                text.len().to_string().as_bytes(),
+                text.as_bytes()
+            ]
+            .concat()
+        );
+
+        let hash = keccak::hash(&message).to_bytes();
+        let (sig, recovery_id) = libsecp256k1::sign(&libsecp256k1::Message::parse(&hash), &secret);
+        let signature = [sig.serialize().as_slice(), &[recovery_id.serialize() + 27]].concat();
+        assert_eq!(recover_eth_address(&message, &signature).unwrap(), address);
+        verify_eth_roa_signature(address, &record_key, &owner, &signature).unwrap();
+
+        // Wrong content, record, staleness ID or recovery ID encoding
+        let other = Pubkey::new_unique();
+        assert!(verify_eth_roa_signature(&[0; 20], &record_key, &owner, &signature).is_err());
+        assert!(verify_eth_roa_signature(address, &other, &owner, &signature).is_err());
+        assert!(verify_eth_roa_signature(address, &record_key, &other, &signature).is_err());
+        let mut raw_v = signature.clone();
+        raw_v[64] -= 27;
+        assert!(verify_eth_roa_signature(address, &record_key, &owner, &raw_v).is_err());
+        assert!(recover_eth_address(&message, &signature[..64]).is_err());
+    }
+
+    /// A TXT record V2 account with the given staleness validation
+    fn staleness_record_data(validation: Validation, staleness_id: &[u8]) -> Vec<u8> {
+        let content = b"hello-";
+        let header = RecordHeader {
+            staleness_validation: validation as u16,
+            right_of_association_validation: Validation::None as u16,
+            content_length: content.len() as u32,
+        };
+        [
+            &[0; NameRecordHeader::LEN],
+            bytemuck::bytes_of(&header),
+            staleness_id,
+            content,
+        ]
+        .concat()
+    }
+
+    #[tokio::test]
+    async fn test_verify_staleness() {
+        use borsh::BorshSerialize;
+        use sns_warp_common::state::x_domain::XDomain;
+
+        let config = &SnsConfig::DEFAULT;
+        let emitter = config.warp_emitter;
+        let owner = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        let check = |data: Vec<u8>, owner: Pubkey, x_domain: Option<Vec<u8>>, valid: bool| async move {
+            let parsed = parse_record_v2(Record::TXT, &data).await.unwrap();
+            let domain_owner = config
+                .get_domain_owner_id(owner, x_domain.as_deref())
+                .unwrap();
+            let res = parsed.verify_staleness(&domain_owner);
+            assert_eq!(res.is_ok(), valid);
+            if let Err(e) = res {
+                assert!(matches!(e, SnsError::StaleRecord));
+            }
+            let report = parsed.verification(&[], domain_owner).unwrap();
+            assert_eq!(report.is_stale(), !valid);
+        };
+
+        // Solana
+        let data = staleness_record_data(Validation::Solana, owner.as_ref());
+        check(data.clone(), owner, None, true).await;
+        check(data, other, None, false).await;
+
+        // Ethereum and UnverifiedSolana do not prove ownership
+        let data = staleness_record_data(Validation::Ethereum, &owner.as_ref()[..20]);
+        check(data, owner, None, false).await;
+        let data = staleness_record_data(Validation::UnverifiedSolana, owner.as_ref());
+        check(data, owner, None, false).await;
+        let data = staleness_record_data(Validation::None, &[]);
+        check(data, owner, None, false).await;
+
+        // XChain
+        let x_domain = |address| {
+            XDomain {
+                nonce: 255,
+                owner_chain: 2,
+                owner_address: address,
+                name_account: Pubkey::new_unique(),
+            }
+            .try_to_vec()
+            .unwrap()
+        };
+        let staleness_id = [[2, 0].as_slice(), &[7; 32]].concat();
+        let data = staleness_record_data(Validation::XChain, &staleness_id);
+        check(data.clone(), emitter, Some(x_domain([7; 32])), true).await;
+        check(data.clone(), emitter, Some(x_domain([8; 32])), false).await;
+        check(data.clone(), emitter, None, false).await;
+        check(data, owner, Some(x_domain([7; 32])), false).await;
+        // A Solana signature of the emitter is not a proof of the XChain owner
+        let data = staleness_record_data(Validation::Solana, emitter.as_ref());
+        check(data.clone(), emitter, Some(x_domain([7; 32])), false).await;
+        // Nor when the XDomain account is missing
+        check(data, emitter, None, false).await;
+    }
+
+    #[tokio::test]
+    async fn test_record_verification() {
+        use crate::test_utils::MockRecordV2;
+
+        let owner = Pubkey::new_unique();
+        let record = MockRecordV2::new(Record::CNAME, "sns.id")
+            .staleness(&owner)
+            .roa(Validation::Solana, GUARDIAN_ID.as_ref());
+        let data = [vec![0; NameRecordHeader::LEN], record.serialize()].concat();
+        let parsed = parse_record_v2(Record::CNAME, &data).await.unwrap();
+
+        let guardians = SnsConfig::DEFAULT.get_guardians(Record::CNAME);
+        assert_eq!(guardians, [GUARDIAN_ID]);
+        let res = parsed
+            .verification(guardians, ValidationId::Solana(owner))
+            .unwrap();
+        assert_eq!(res.record_owner, ValidationId::Solana(owner));
+        assert_eq!(res.roa_signer, ValidationId::Solana(GUARDIAN_ID));
+        assert_eq!(res.roa_guardian, Some(GUARDIAN_ID));
+        // Punycode encoded
+        assert_eq!(res.content_length, "sns.id-".len() as u32);
+        assert_eq!(res.status(), RecordStatus::Verified);
+
+        let new_owner = Pubkey::new_unique();
+        let res = parsed
+            .verification(guardians, ValidationId::Solana(new_owner))
+            .unwrap();
+        assert_eq!(res.domain_owner, ValidationId::Solana(new_owner));
+        assert_eq!(res.status(), RecordStatus::Stale);
+
+        // Right of association signed by someone else than the guardian
+        let record = record.roa(Validation::Solana, owner.as_ref());
+        let data = [vec![0; NameRecordHeader::LEN], record.serialize()].concat();
+        let res = split_record_v2(&data)
+            .unwrap()
+            .verification(Record::CNAME, guardians, ValidationId::Solana(owner))
+            .unwrap();
+        assert_eq!(res.roa_signer, ValidationId::Solana(owner));
+        assert_eq!(res.roa_guardian, None);
+        assert_eq!(res.status(), RecordStatus::UnverifiedRoa);
+
+        // Callers running their own guardian can trust it
+        let own_guardian = Pubkey::new_unique();
+        let record = record.roa(Validation::Solana, own_guardian.as_ref());
+        let data = [vec![0; NameRecordHeader::LEN], record.serialize()].concat();
+        let parsed = parse_record_v2(Record::CNAME, &data).await.unwrap();
+        assert!(parsed.verify_roa(guardians).is_err());
+        let own_guardians = [GUARDIAN_ID, own_guardian];
+        assert_eq!(
+            parsed.verify_roa(&own_guardians).unwrap(),
+            Some(own_guardian)
+        );
+        let res = parsed
+            .verification(&own_guardians, ValidationId::Solana(owner))
+            .unwrap();
+        assert_eq!(res.roa_guardian, Some(own_guardian));
+        assert_eq!(res.status(), RecordStatus::Verified);
+
+        // Without guardians, the content of a CNAME record cannot validate its right of
+        // association
+        assert!(matches!(
+            parsed.verify_roa(&[]),
+            Err(SnsError::UnverifiedRecord)
+        ));
+        let res = parsed
+            .verification(&[], ValidationId::Solana(owner))
+            .unwrap();
+        assert_eq!(res.status(), RecordStatus::UnverifiedRoa);
+
+        // The right of association of an EVM record is validated by its content
+        let address = "0x4bfBFD1E018F9F27eeB788160579daF7E2cd7dA7";
+        let eth = hex::decode(&address[2..]).unwrap();
+        for (roa_id, verified) in [(eth.clone(), true), (vec![1; 20], false)] {
+            let record = MockRecordV2::new(Record::Eth, address)
+                .staleness(&owner)
+                .roa(Validation::Ethereum, &roa_id);
+            let data = [vec![0; NameRecordHeader::LEN], record.serialize()].concat();
+            let parsed = parse_record_v2(Record::Eth, &data).await.unwrap();
+            assert_eq!(parsed.verify_roa(&[]).is_ok(), verified);
+            let res = parsed
+                .verification(&[], ValidationId::Solana(owner))
+                .unwrap();
+            assert_eq!(
+                res.expected_roa_signer,
+                ValidationId::Ethereum(eth.clone().try_into().unwrap())
+            );
+            assert_eq!(res.is_roa_verified(), verified);
+        }
+
+        let staleness_id = [[2, 0].as_slice(), &[7; 32]].concat();
+        assert_eq!(
+            ValidationId::parse(Validation::XChain, &staleness_id).unwrap(),
+            ValidationId::XChain {
+                chain: 2,
+                address: [7; 32]
+            }
+        );
+        assert!(ValidationId::parse(Validation::Ethereum, &[0; 32]).is_err());
+    }
+
+    #[test]
+    fn test_record_v2_instructions() {
+        use borsh::BorshSerialize;
+        use sns_records::instruction::ProgramInstruction;
+
+        let owner = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        let domain_key = crate::derivation::get_domain_key("dex.bonfida").unwrap();
+        let (record_key, _) = sns_records::utils::get_record_key_and_seeds(&domain_key, "\x02SOL");
+        assert_eq!(
+            record_key,
+            crate::record::get_record_v2_key("dex.bonfida", Record::Sol).unwrap()
+        );
+
+        let content = "D8mRVSXrE2uU8KDAKQsGbfBNRyunMrmHBdEMrtWz1cUc";
+        let ix = create_record_v2_instruction("dex.bonfida", Record::Sol, content, &owner, &payer)
+            .unwrap();
+        assert_eq!(ix.program_id, sns_records::ID);
+        let keys = ix.accounts.iter().map(|a| a.pubkey).collect::<Vec<_>>();
+        assert_eq!(
+            keys,
+            [
+                system_program::ID,
+                spl_name_service::ID,
+                payer,
+                record_key,
+                domain_key,
+                owner,
+                CENTRAL_STATE_RECORD_V2
+            ]
+        );
+        let params = allocate_and_post_record::Params {
+            record: "\x02SOL".to_owned(),
+            content: content.parse::<Pubkey>().unwrap().to_bytes().to_vec(),
+        };
+        let expected = [
+            vec![ProgramInstruction::AllocateAndPostRecord as u8],
+            params.try_to_vec().unwrap(),
+        ]
+        .concat();
+        assert_eq!(ix.data, expected);
+
+        let ix =
+            validate_record_v2_content(true, "dex.bonfida", Record::Sol, &owner, &payer, &owner)
+                .unwrap();
+        assert_eq!(ix.accounts.len(), 8);
+        assert_eq!(ix.accounts[7].pubkey, owner);
+        assert!(ix.accounts[7].is_signer);
+
+        assert!(
+            update_record_v2_instruction("dex.bonfida", Record::Eth, "0x12", &owner, &payer)
+                .is_err()
+        );
     }
 }
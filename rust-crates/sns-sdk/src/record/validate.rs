@@ -0,0 +1,364 @@
+//! Validation of the content of textual records before they are written
+//!
+//! Binary encoded records are validated when they are parsed, see `RecordValue::parse`.
+
+use data_encoding::{BASE32_NOPAD, BASE64, BASE64URL, BASE64URL_NOPAD};
+
+use super::{value::parse_evm_address, Record};
+use crate::error::SnsError;
+
+/// The maximum length of the content of a textual record, so that it fits in a single transaction
+pub const MAX_TEXT_RECORD_LEN: usize = 512;
+
+/// Checks that `content` is valid for `record`, e.g. that an `Email` record holds an email
+/// address
+pub fn validate_record_content(record: Record, content: &str) -> Result<(), SnsError> {
+    if content.len() > MAX_TEXT_RECORD_LEN {
+        return Err(SnsError::RecordContentTooLong);
+    }
+    match record {
+        Record::Url => validate_url(content, &["http", "https"]),
+        Record::Pic => validate_url(content, &["http", "https", "ipfs", "ar", "data"]),
+        Record::Email => validate_email(content),
+        Record::Ipfs => validate_ipfs_cid(content.strip_prefix("ipfs://").unwrap_or(content)),
+        Record::Arwv => validate_arweave_id(content),
+        Record::Polygon | Record::Arbitrum => parse_evm_address(content).map(|_| ()),
+        Record::Sui | Record::Aptos => validate_move_address(content),
+        Record::Stellar => validate_stellar_address(content),
+        Record::Ton => validate_ton_address(content),
+        Record::Twitter => {
+            let handle = content.strip_prefix('@').unwrap_or(content);
+            check_handle(is_handle(handle, 1..=15, |c| c == '_'))
+        }
+        Record::Telegram => {
+            let handle = content.strip_prefix('@').unwrap_or(content);
+            check_handle(
+                is_handle(handle, 5..=32, |c| c == '_')
+                    && handle.starts_with(|c: char| c.is_ascii_alphabetic())
+                    && !handle.ends_with('_'),
+            )
+        }
+        Record::Github => check_handle(
+            is_handle(content, 1..=39, |c| c == '-')
+                && !content.starts_with('-')
+                && !content.ends_with('-')
+                && !content.contains("--"),
+        ),
+        Record::Reddit => {
+            let handle = ["/u/", "u/"]
+                .iter()
+                .find_map(|prefix| content.strip_prefix(prefix))
+                .unwrap_or(content);
+            check_handle(is_handle(handle, 3..=20, |c| c == '_' || c == '-'))
+        }
+        Record::Discord => check_handle(match content.split_once('#') {
+            // Legacy username with a discriminator
+            Some((name, discriminator)) => {
+                (2..=32).contains(&name.chars().count())
+                    && !name.contains(['@', ':'])
+                    && discriminator.len() == 4
+                    && discriminator.bytes().all(|b| b.is_ascii_digit())
+            }
+            None => {
+                is_handle(content, 2..=32, |c| c == '_' || c == '.')
+                    && !content.bytes().any(|b| b.is_ascii_uppercase())
+                    && !content.contains("..")
+            }
+        }),
+        _ => Ok(()),
+    }
+}
+
+fn validate_url(content: &str, schemes: &[&str]) -> Result<(), SnsError> {
+    let url = url::Url::parse(content).map_err(|_| SnsError::InvalidUrl)?;
+    if !schemes.is_empty() && !schemes.contains(&url.scheme()) {
+        return Err(SnsError::InvalidUrl);
+    }
+    if url.scheme().starts_with("http") && url.host_str().unwrap_or_default().is_empty() {
+        return Err(SnsError::InvalidUrl);
+    }
+    Ok(())
+}
+
+/// A subset of the RFC 5322 `addr-spec`, without quoted strings, comments or IP literals
+fn validate_email(content: &str) -> Result<(), SnsError> {
+    let (local, domain) = content.rsplit_once('@').ok_or(SnsError::InvalidEmail)?;
+    let local_valid = (1..=64).contains(&local.len())
+        && local.split('.').all(|atom| {
+            !atom.is_empty()
+                && atom
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+/=?^_`{|}~-".contains(c))
+        });
+    let labels = domain.split('.').collect::<Vec<_>>();
+    let domain_valid = domain.len() <= 253
+        && labels.len() >= 2
+        && labels.iter().all(|label| {
+            (1..=63).contains(&label.len())
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+    if content.len() > 254 || !local_valid || !domain_valid {
+        return Err(SnsError::InvalidEmail);
+    }
+    Ok(())
+}
+
+/// Accepts a CIDv0 or a base32 (`b`) or base58btc (`z`) CIDv1
+fn validate_ipfs_cid(content: &str) -> Result<(), SnsError> {
+    if content.len() == 46 && content.starts_with("Qm") {
+        let data = bs58::decode(content)
+            .into_vec()
+            .map_err(|_| SnsError::InvalidIpfsCid)?;
+        // sha2-256 multihash
+        return match data[..] {
+            [0x12, 0x20, ..] if data.len() == 34 => Ok(()),
+            _ => Err(SnsError::InvalidIpfsCid),
+        };
+    }
+    let data = match content.split_at(content.len().min(1)) {
+        ("b", data) => BASE32_NOPAD
+            .decode(data.to_ascii_uppercase().as_bytes())
+            .ok(),
+        ("z", data) => bs58::decode(data).into_vec().ok(),
+        _ => None,
+    }
+    .ok_or(SnsError::InvalidIpfsCid)?;
+
+    let mut data = data.as_slice();
+    let version = read_varint(&mut data)?;
+    let _codec = read_varint(&mut data)?;
+    let _hash_function = read_varint(&mut data)?;
+    let digest_len = read_varint(&mut data)?;
+    if version != 1 || digest_len != data.len() as u64 || data.is_empty() {
+        return Err(SnsError::InvalidIpfsCid);
+    }
+    Ok(())
+}
+
+/// Reads an unsigned LEB128 varint, as used by multiformats
+fn read_varint(data: &mut &[u8]) -> Result<u64, SnsError> {
+    let mut value = 0u64;
+    for (idx, &byte) in data.iter().enumerate().take(9) {
+        value |= ((byte & 0x7f) as u64) << (7 * idx);
+        if byte & 0x80 == 0 {
+            *data = &data[idx + 1..];
+            return Ok(value);
+        }
+    }
+    Err(SnsError::InvalidIpfsCid)
+}
+
+/// An Arweave transaction ID, the base64url encoding of 32 bytes
+fn validate_arweave_id(content: &str) -> Result<(), SnsError> {
+    match BASE64URL_NOPAD.decode(content.as_bytes()) {
+        Ok(data) if data.len() == 32 => Ok(()),
+        _ => Err(SnsError::InvalidArweaveId),
+    }
+}
+
+/// A full or short (leading zeros omitted) `0x` prefixed Move address
+fn validate_move_address(content: &str) -> Result<(), SnsError> {
+    match content.strip_prefix("0x") {
+        Some(hex)
+            if (1..=64).contains(&hex.len()) && hex.bytes().all(|b| b.is_ascii_hexdigit()) =>
+        {
+            Ok(())
+        }
+        _ => Err(SnsError::InvalidMoveAddress),
+    }
+}
+
+/// Version byte of the strkey of an ed25519 account
+const STELLAR_ACCOUNT_VERSION: u8 = 6 << 3;
+
+/// The strkey of a Stellar account
+fn validate_stellar_address(content: &str) -> Result<(), SnsError> {
+    let data = BASE32_NOPAD
+        .decode(content.as_bytes())
+        .map_err(|_| SnsError::InvalidStellarAddress)?;
+    match data[..] {
+        [STELLAR_ACCOUNT_VERSION, ref key @ .., c0, c1]
+            if key.len() == 32
+                && u16::from_le_bytes([c0, c1]) == crc16(&data[..data.len() - 2]) =>
+        {
+            Ok(())
+        }
+        _ => Err(SnsError::InvalidStellarAddress),
+    }
+}
+
+/// A user-friendly TON address, in either base64 or base64url
+fn validate_ton_address(content: &str) -> Result<(), SnsError> {
+    let data = BASE64URL
+        .decode(content.as_bytes())
+        .or_else(|_| BASE64.decode(content.as_bytes()))
+        .map_err(|_| SnsError::InvalidTonAddress)?;
+    match data[..] {
+        // Bounceable or non-bounceable, with the optional testnet bit, then the workchain and the
+        // hash of the account
+        [flags, _, ref hash @ .., c0, c1]
+            if matches!(flags & 0x7f, 0x11 | 0x51)
+                && hash.len() == 32
+                && u16::from_be_bytes([c0, c1]) == crc16(&data[..data.len() - 2]) =>
+        {
+            Ok(())
+        }
+        _ => Err(SnsError::InvalidTonAddress),
+    }
+}
+
+/// CRC-16/XMODEM, used by the Stellar and TON address checksums
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0, |crc, &byte| {
+        (0..8).fold(crc ^ ((byte as u16) << 8), |crc, _| {
+            if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+fn is_handle(
+    handle: &str,
+    len: std::ops::RangeInclusive<usize>,
+    allowed: impl Fn(char) -> bool,
+) -> bool {
+    len.contains(&handle.len())
+        && handle
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || allowed(c))
+}
+
+fn check_handle(valid: bool) -> Result<(), SnsError> {
+    valid.then_some(()).ok_or(SnsError::InvalidHandle)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_validate_record_content() {
+        let valid = [
+            (Record::Url, "https://sns.id/domain?name=bonfida"),
+            (
+                Record::Pic,
+                "ipfs://QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG",
+            ),
+            (Record::Pic, "https://sns.id/pic.png"),
+            (Record::Pic, "data:image/png;base64,iVBORw0KGgo="),
+            (Record::Email, "contact@bonfida.com"),
+            (Record::Email, "first.last+sns@mail.example.org"),
+            (
+                Record::Ipfs,
+                "QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbdG",
+            ),
+            (
+                Record::Ipfs,
+                "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi",
+            ),
+            (Record::Arwv, "bNbA3TEQVL60xlgCcqdz4ZPHFZ711cZ3hmkpGttDt_U"),
+            (Record::Twitter, "@bonfida"),
+            (Record::Telegram, "bonfida_sns"),
+            (Record::Github, "bonfida"),
+            (Record::Reddit, "u/bonfida"),
+            (Record::Discord, "bonfida.sns"),
+            (Record::Discord, "Bonfida#1234"),
+            (Record::TXT, "v=spf1 include:_spf.google.com ~all"),
+            (
+                Record::IPNS,
+                "k51qzi5uqu5dlvj2baxnqndepeb86cbk3ng7n3i46uzyxzyqj2xjonzllnv0v8",
+            ),
+            (
+                Record::Polygon,
+                "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            ),
+            (
+                Record::Arbitrum,
+                "0xdbf03b407c01e7cd3cbea99509d93f8dddc8c6fb",
+            ),
+            (
+                Record::Sui,
+                "0x02a212de6a9dfa3a69e22387acfbafbb1a9e591bd9d636e7895dcfc8de05f331",
+            ),
+            (Record::Aptos, "0x1"),
+            // SEP-23 test vector
+            (
+                Record::Stellar,
+                "GA7QYNF7SOWQ3GLR2BGMZEHXAVIRZA4KVWLTJJFC7MGXUA74P7UJVSGZ",
+            ),
+            (
+                Record::Ton,
+                "EQDKbjIcfM6ezt8KjKJJLshZJJSqX7XOA4ff-W72r5gqPrHF",
+            ),
+            (
+                Record::Ton,
+                "UQDKbjIcfM6ezt8KjKJJLshZJJSqX7XOA4ff-W72r5gqPuwA",
+            ),
+            (
+                Record::Ton,
+                "EQDKbjIcfM6ezt8KjKJJLshZJJSqX7XOA4ff+W72r5gqPrHF",
+            ),
+        ];
+        for (record, content) in valid {
+            assert!(
+                validate_record_content(record, content).is_ok(),
+                "{record:?} {content}"
+            );
+        }
+
+        let invalid = [
+            (Record::Url, "sns.id"),
+            (Record::Url, "ftp://sns.id"),
+            (Record::Pic, "javascript:alert(1)"),
+            (Record::Pic, "file:///etc/passwd"),
+            (Record::Email, "bonfida.com"),
+            (Record::Email, "contact@bonfida"),
+            (Record::Email, "contact..sns@bonfida.com"),
+            (
+                Record::Ipfs,
+                "QmYwAPJzv5CZsnA625s3Xf2nemtYgPpHdWEz79ojWnPbd",
+            ),
+            (
+                Record::Ipfs,
+                "bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzd",
+            ),
+            (Record::Arwv, "bNbA3TEQVL60xlgCcqdz4ZPHFZ711cZ3hmkpGttDt"),
+            (Record::Twitter, "bonfida_is_too_long"),
+            (Record::Telegram, "sns"),
+            (Record::Github, "-bonfida"),
+            (Record::Reddit, "u/b"),
+            (Record::Discord, "Bonfida"),
+            (Record::Polygon, "0x1234"),
+            (Record::Sui, "0x"),
+            (
+                Record::Sui,
+                "02a212de6a9dfa3a69e22387acfbafbb1a9e591bd9d636e7895dcfc8de05f331",
+            ),
+            (Record::Aptos, "0xg1"),
+            (
+                Record::Stellar,
+                "GA7QYNF7SOWQ3GLR2BGMZEHXAVIRZA4KVWLTJJFC7MGXUA74P7UJVSGA",
+            ),
+            (
+                Record::Ton,
+                "EQDKbjIcfM6ezt8KjKJJLshZJJSqX7XOA4ff-W72r5gqPrHG",
+            ),
+        ];
+        for (record, content) in invalid {
+            assert!(
+                validate_record_content(record, content).is_err(),
+                "{record:?} {content}"
+            );
+        }
+
+        assert!(matches!(
+            validate_record_content(Record::TXT, &"a".repeat(MAX_TEXT_RECORD_LEN + 1)),
+            Err(SnsError::RecordContentTooLong)
+        ));
+    }
+}
@@ -0,0 +1,376 @@
+use std::{
+    fmt,
+    net::{Ipv4Addr, Ipv6Addr},
+    str::FromStr,
+};
+
+use bech32::{FromBase32, ToBase32};
+use solana_program::{hash, keccak, pubkey::Pubkey};
+
+use super::{Record, RecordEncoding};
+use crate::error::SnsError;
+
+/// The typed content of a record
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum RecordValue {
+    Pubkey(Pubkey),
+    EvmAddress([u8; 20]),
+    Ipv4(Ipv4Addr),
+    Ipv6(Ipv6Addr),
+    Bech32(Bech32Address),
+    Url(String),
+    Text(String),
+    /// The content of a record which does not match its encoding
+    Raw(Vec<u8>),
+}
+
+impl RecordValue {
+    /// Parses the string representation of the content of `record`
+    pub fn parse(record: Record, content: &str) -> Result<Self, SnsError> {
+        match record.encoding() {
+            RecordEncoding::Utf8 | RecordEncoding::Punycode => Ok(Self::text(record, content)),
+            RecordEncoding::Pubkey => Pubkey::from_str(content)
+                .map(Self::Pubkey)
+                .map_err(|_| SnsError::InvalidPubkey),
+            RecordEncoding::Evm => parse_evm_address(content).map(Self::EvmAddress),
+            RecordEncoding::Injective => {
+                if !content.starts_with("inj") {
+                    return Err(SnsError::InvalidInjectiveAddress);
+                }
+                let address = content.parse::<Bech32Address>()?;
+                if address.hrp() != "inj" || address.data().len() != 20 {
+                    return Err(SnsError::InvalidInjectiveAddress);
+                }
+                Ok(Self::Bech32(address))
+            }
+            RecordEncoding::Bitcoin => {
+                validate_bitcoin_address(content)?;
+                Ok(Self::Text(content.to_owned()))
+            }
+            RecordEncoding::Ipv4 => content
+                .parse()
+                .map(Self::Ipv4)
+                .map_err(|_| SnsError::InvalidIpv4),
+            RecordEncoding::Ipv6 => content
+                .parse()
+                .map(Self::Ipv6)
+                .map_err(|_| SnsError::InvalidIpv6),
+        }
+    }
+
+    /// Deserializes the content of a record V2
+    pub fn from_bytes(record: Record, content: &[u8]) -> Result<Self, SnsError> {
+        match record.encoding() {
+            RecordEncoding::Utf8 => Ok(Self::text(record, &String::from_utf8(content.to_vec())?)),
+            RecordEncoding::Punycode => {
+                let decoded = String::from_utf8(content.to_vec())?;
+                let decoded = punycode::decode(&decoded).map_err(|_| SnsError::Punycode)?;
+                Ok(Self::text(record, &decoded))
+            }
+            RecordEncoding::Pubkey => Ok(Self::Pubkey(Pubkey::new_from_array(content.try_into()?))),
+            RecordEncoding::Evm => Ok(Self::EvmAddress(content.try_into()?)),
+            RecordEncoding::Injective => Ok(Self::Bech32(Bech32Address::new("inj", content)?)),
+            RecordEncoding::Bitcoin => {
+                let decoded = String::from_utf8(content.to_vec())?;
+                Self::parse(record, &decoded)
+            }
+            RecordEncoding::Ipv4 => Ok(Self::Ipv4(Ipv4Addr::from(<[u8; 4]>::try_from(content)?))),
+            RecordEncoding::Ipv6 => Ok(Self::Ipv6(Ipv6Addr::from(<[u8; 16]>::try_from(content)?))),
+        }
+    }
+
+    /// Serializes the value as the content of a record V2, the value has to match the encoding of
+    /// `record`
+    pub fn to_bytes(&self, record: Record) -> Result<Vec<u8>, SnsError> {
+        match (record.encoding(), self) {
+            (RecordEncoding::Utf8, Self::Url(s) | Self::Text(s)) => Ok(s.as_bytes().to_vec()),
+            (RecordEncoding::Punycode, Self::Url(s) | Self::Text(s)) => {
+                let encoded = punycode::encode(s).map_err(|_| SnsError::Punycode)?;
+                Ok(encoded.into_bytes())
+            }
+            (RecordEncoding::Pubkey, Self::Pubkey(pubkey)) => Ok(pubkey.to_bytes().to_vec()),
+            (RecordEncoding::Evm, Self::EvmAddress(address)) => Ok(address.to_vec()),
+            (RecordEncoding::Injective, Self::Bech32(address))
+                if address.hrp() == "inj" && address.data().len() == 20 =>
+            {
+                Ok(address.data().to_vec())
+            }
+            (RecordEncoding::Bitcoin, Self::Text(s)) => {
+                validate_bitcoin_address(s)?;
+                Ok(s.as_bytes().to_vec())
+            }
+            (RecordEncoding::Ipv4, Self::Ipv4(ip)) => Ok(ip.octets().to_vec()),
+            (RecordEncoding::Ipv6, Self::Ipv6(ip)) => Ok(ip.octets().to_vec()),
+            (_, Self::Raw(content)) => Ok(content.clone()),
+            _ => Err(SnsError::InvalidRecordValue),
+        }
+    }
+
+    fn text(record: Record, content: &str) -> Self {
+        if record == Record::Url {
+            Self::Url(content.to_owned())
+        } else {
+            Self::Text(content.to_owned())
+        }
+    }
+}
+
+impl fmt::Display for RecordValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pubkey(pubkey) => write!(f, "{pubkey}"),
+            Self::EvmAddress(address) => f.write_str(&to_checksum_address(address)),
+            Self::Ipv4(ip) => write!(f, "{ip}"),
+            Self::Ipv6(ip) => write!(f, "{ip}"),
+            Self::Bech32(address) => write!(f, "{address}"),
+            Self::Url(s) | Self::Text(s) => f.write_str(s),
+            Self::Raw(content) => write!(f, "0x{}", hex::encode(content)),
+        }
+    }
+}
+
+/// Infers the kind of value from its string representation, falling back to `Text`
+///
+/// Use `RecordValue::parse` when the record is known.
+impl FromStr for RecordValue {
+    type Err = SnsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(address) = parse_evm_address(s) {
+            return Ok(Self::EvmAddress(address));
+        }
+        if let Ok(ip) = s.parse() {
+            return Ok(Self::Ipv4(ip));
+        }
+        if let Ok(ip) = s.parse() {
+            return Ok(Self::Ipv6(ip));
+        }
+        if s.starts_with("https://") || s.starts_with("http://") {
+            return Ok(Self::Url(s.to_owned()));
+        }
+        if let Ok(pubkey) = Pubkey::from_str(s) {
+            return Ok(Self::Pubkey(pubkey));
+        }
+        // Segwit addresses are bech32 but prefix their data with the witness version
+        if validate_bitcoin_address(s).is_ok() {
+            return Ok(Self::Text(s.to_owned()));
+        }
+        if let Ok(address) = s.parse() {
+            return Ok(Self::Bech32(address));
+        }
+        Ok(Self::Text(s.to_owned()))
+    }
+}
+
+/// A bech32 encoded address, e.g an Injective address
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Bech32Address {
+    hrp: String,
+    data: Vec<u8>,
+    /// The address, which is validated on creation so that it can always be displayed
+    encoded: String,
+}
+
+impl Bech32Address {
+    pub fn new(hrp: &str, data: &[u8]) -> Result<Self, SnsError> {
+        let encoded = bech32::encode(hrp, data.to_base32(), bech32::Variant::Bech32)?;
+        Ok(Self {
+            hrp: hrp.to_owned(),
+            data: data.to_vec(),
+            encoded,
+        })
+    }
+
+    pub fn hrp(&self) -> &str {
+        &self.hrp
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl fmt::Display for Bech32Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.encoded)
+    }
+}
+
+/// Only accepts addresses which are displayed the same, i.e lowercase bech32 (not bech32m)
+/// addresses whose data is a whole number of bytes
+impl FromStr for Bech32Address {
+    type Err = SnsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (hrp, data, variant) = bech32::decode(s)?;
+        let address = Self::new(&hrp, &Vec::<u8>::from_base32(&data)?)?;
+        if variant != bech32::Variant::Bech32 || address.encoded != s {
+            return Err(SnsError::InvalidRecordValue);
+        }
+        Ok(address)
+    }
+}
+
+pub(super) fn parse_evm_address(s: &str) -> Result<[u8; 20], SnsError> {
+    let hex = s.strip_prefix("0x").ok_or(SnsError::InvalidEvmAddress)?;
+    hex::decode(hex)?
+        .try_into()
+        .map_err(|_| SnsError::InvalidEvmAddress)
+}
+
+/// Checks a mainnet or testnet Bitcoin address, either base58check (P2PKH, P2SH) or segwit
+/// (BIP-173, BIP-350)
+fn validate_bitcoin_address(s: &str) -> Result<(), SnsError> {
+    if let Ok((hrp, data, variant)) = bech32::decode(s) {
+        let (version, program) = data.split_first().ok_or(SnsError::InvalidBitcoinAddress)?;
+        let program = Vec::<u8>::from_base32(program)?;
+        let valid = matches!(hrp.as_str(), "bc" | "tb")
+            && match version.to_u8() {
+                0 => variant == bech32::Variant::Bech32 && matches!(program.len(), 20 | 32),
+                1..=16 => variant == bech32::Variant::Bech32m && (2..=40).contains(&program.len()),
+                _ => false,
+            };
+        return valid.then_some(()).ok_or(SnsError::InvalidBitcoinAddress);
+    }
+    let data = bs58::decode(s)
+        .into_vec()
+        .map_err(|_| SnsError::InvalidBitcoinAddress)?;
+    // Mainnet then testnet P2PKH and P2SH versions
+    if data.len() != 25 || !matches!(data[0], 0x00 | 0x05 | 0x6f | 0xc4) {
+        return Err(SnsError::InvalidBitcoinAddress);
+    }
+    let (payload, checksum) = data.split_at(21);
+    let digest = hash::hash(hash::hash(payload).as_ref());
+    if &digest.as_ref()[..4] != checksum {
+        return Err(SnsError::InvalidBitcoinAddress);
+    }
+    Ok(())
+}
+
+/// Formats an EVM address with the EIP-55 mixed case checksum
+pub fn to_checksum_address(address: &[u8; 20]) -> String {
+    let lower = hex::encode(address);
+    let hash = keccak::hash(lower.as_bytes()).to_bytes();
+    let checksummed = lower
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = (hash[i / 2] >> (4 * (1 - i % 2))) & 0xf;
+            if nibble >= 8 {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        })
+        .collect::<String>();
+    format!("0x{checksummed}")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_checksum_address() {
+        // Test vectors from EIP-55
+        for address in [
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+            "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+            "0x570eDC13f9D406a2b4E6477Ddf75D5E9cCF51cd6",
+        ] {
+            let value = RecordValue::parse(Record::Eth, &address.to_lowercase()).unwrap();
+            assert_eq!(value.to_string(), address);
+        }
+    }
+
+    #[test]
+    fn test_record_value() {
+        let items = [
+            (Record::Sol, "D8mRVSXrE2uU8KDAKQsGbfBNRyunMrmHBdEMrtWz1cUc"),
+            (Record::BASE, "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"),
+            (Record::A, "192.168.0.1"),
+            (Record::AAAA, "2001:db8::1"),
+            (
+                Record::Injective,
+                "inj1l3vt52kqzlvpaw2wfug45qkyncflq8hgr5nem7",
+            ),
+            (Record::Url, "https://sns.id"),
+            (Record::CNAME, "sns.id"),
+        ];
+        for (record, content) in items {
+            let value = RecordValue::parse(record, content).unwrap();
+            assert_eq!(value.to_string(), content);
+            assert_eq!(content.parse::<RecordValue>().unwrap(), value);
+            let bytes = value.to_bytes(record).unwrap();
+            assert_eq!(RecordValue::from_bytes(record, &bytes).unwrap(), value);
+        }
+
+        assert!(matches!(
+            RecordValue::parse(Record::A, "192.168.0.1")
+                .unwrap()
+                .to_bytes(Record::AAAA),
+            Err(SnsError::InvalidRecordValue)
+        ));
+        assert!(RecordValue::parse(Record::Eth, "0x1234").is_err());
+        assert!(Bech32Address::new("", &[1, 2, 3]).is_err());
+        assert!(RecordValue::from_bytes(Record::Injective, &[0; 20]).is_ok());
+    }
+
+    #[test]
+    fn test_from_str() {
+        for address in [
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+            "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0",
+            "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7",
+        ] {
+            let value = address.parse::<RecordValue>().unwrap();
+            assert_eq!(value, RecordValue::Text(address.to_owned()));
+            assert_eq!(value.to_string(), address);
+        }
+        let value = "inj1l3vt52kqzlvpaw2wfug45qkyncflq8hgr5nem7"
+            .parse::<RecordValue>()
+            .unwrap();
+        assert!(matches!(value, RecordValue::Bech32(_)));
+        // Uppercase addresses would not be displayed the same
+        assert_eq!(
+            "INJ1L3VT52KQZLVPAW2WFUG45QKYNCFLQ8HGR5NEM7"
+                .parse::<RecordValue>()
+                .unwrap(),
+            RecordValue::Text("INJ1L3VT52KQZLVPAW2WFUG45QKYNCFLQ8HGR5NEM7".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_bitcoin_addresses() {
+        for content in [
+            "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2",
+            "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy",
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+            "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7",
+            "mipcBbFg9gMiCh81Kj8tqqdgoZub1ZJRfn",
+            "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0",
+        ] {
+            let value = RecordValue::parse(Record::Btc, content).unwrap();
+            assert_eq!(value.to_string(), content);
+            let bytes = value.to_bytes(Record::Btc).unwrap();
+            assert_eq!(bytes, content.as_bytes());
+            assert_eq!(RecordValue::from_bytes(Record::Btc, &bytes).unwrap(), value);
+        }
+
+        for content in [
+            // Bad checksums
+            "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN3",
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5",
+            // Segwit v0 with a bech32m checksum
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kemeawh",
+            "not an address",
+        ] {
+            assert!(
+                RecordValue::parse(Record::Btc, content).is_err(),
+                "{content}"
+            );
+        }
+    }
+}
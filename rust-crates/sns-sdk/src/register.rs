@@ -2,10 +2,13 @@ use bonfida_utils::InstructionsAccount;
 use solana_program::{instruction::Instruction, pubkey};
 use solana_sdk::pubkey::Pubkey;
 
-pub use constants::*;
-
 #[cfg(not(feature = "devnet"))]
-mod constants {
+pub use mainnet::*;
+
+#[cfg(feature = "devnet")]
+pub use devnet::*;
+
+pub mod mainnet {
     use super::*;
 
     pub const REGISTER_PROGRAM_ID: Pubkey = pubkey!("jCebN34bUfdeUYJT13J1yG16XWQpt5PDx6Mse9GUqhR");
@@ -73,8 +76,7 @@ mod constants {
     pub const VAULT_OWNER: Pubkey = pubkey!("GcWEQ9K78FV7LEHteFVciYApERk5YvQuFDQPk1yYJVXi");
 }
 
-#[cfg(feature = "devnet")]
-mod constants {
+pub mod devnet {
     use super::*;
 
     pub const REGISTER_PROGRAM_ID: Pubkey = pubkey!("snshBoEQ9jx4QoHBpZDQPYdNCtw7RMxJvYrKFEhwaPJ");
@@ -0,0 +1,350 @@
+//! A mock ledger to exercise the SDK without an RPC node
+//!
+//! ```ignore
+//! let fetcher = LedgerBuilder::new()
+//!     .domain("bonfida", owner)
+//!     .domain("dex.bonfida", owner)
+//!     .record_v1("bonfida", Record::Url, b"https://sns.id")
+//!     .tokenize("bonfida", holder)
+//!     .build();
+//! assert_eq!(resolve_owner(&fetcher, "bonfida").await?, Some(holder));
+//! ```
+//!
+//! Builder methods panic on invalid input, e.g. when adding a record to a domain that was not
+//! registered first.
+use std::collections::{BTreeMap, HashMap};
+
+use borsh::BorshSerialize;
+use name_tokenizer::state::NftRecord;
+use sns_records::state::{record_header::RecordHeader, validation::Validation};
+use sns_warp_common::state::x_domain::XDomain;
+use solana_program::{program_option::COption, program_pack::Pack, pubkey::Pubkey, rent::Rent};
+use solana_sdk::{account::Account, signature::Keypair};
+use spl_name_service::state::NameRecordHeader;
+use spl_token::state::{Account as TokenAccount, AccountState, Mint};
+
+use crate::{
+    config::SnsConfig,
+    fetcher::InMemoryFetcher,
+    record::{
+        record_v1::sign_sol_record, record_v2::serialize_record_v2_content_unchecked, Record,
+        RecordKey, RecordVersion, CENTRAL_STATE_RECORD_V2,
+    },
+};
+
+/// The content and validations of a mock record V2
+#[derive(Clone, Debug)]
+pub struct MockRecordV2 {
+    pub content: Vec<u8>,
+    pub staleness_validation: Validation,
+    pub staleness_id: Vec<u8>,
+    pub roa_validation: Validation,
+    pub roa_id: Vec<u8>,
+}
+
+impl MockRecordV2 {
+    /// An unverified record with serialized `content`
+    pub fn new(record: Record, content: &str) -> Self {
+        Self {
+            content: serialize_record_v2_content_unchecked(content, record)
+                .expect("Invalid record V2 content"),
+            staleness_validation: Validation::None,
+            staleness_id: vec![],
+            roa_validation: Validation::None,
+            roa_id: vec![],
+        }
+    }
+
+    /// An unverified record holding raw `content`
+    pub fn raw(content: &[u8]) -> Self {
+        Self {
+            content: content.to_vec(),
+            staleness_validation: Validation::None,
+            staleness_id: vec![],
+            roa_validation: Validation::None,
+            roa_id: vec![],
+        }
+    }
+
+    /// Marks the record as signed by `owner` for staleness
+    pub fn staleness(mut self, owner: &Pubkey) -> Self {
+        self.staleness_validation = Validation::Solana;
+        self.staleness_id = owner.to_bytes().to_vec();
+        self
+    }
+
+    /// Marks the record as signed for staleness by the owner of a bridged domain
+    pub fn xchain_staleness(mut self, chain: u16, address: &[u8; 32]) -> Self {
+        self.staleness_validation = Validation::XChain;
+        self.staleness_id = [&chain.to_le_bytes()[..], address].concat();
+        self
+    }
+
+    pub fn roa(mut self, validation: Validation, id: &[u8]) -> Self {
+        self.roa_validation = validation;
+        self.roa_id = id.to_vec();
+        self
+    }
+
+    /// The data of the record account following the name registry header
+    pub fn serialize(&self) -> Vec<u8> {
+        let header = RecordHeader {
+            staleness_validation: self.staleness_validation as u16,
+            right_of_association_validation: self.roa_validation as u16,
+            content_length: self.content.len() as u32,
+        };
+        [
+            bytemuck::bytes_of(&header),
+            &self.staleness_id,
+            &self.roa_id,
+            &self.content,
+        ]
+        .concat()
+    }
+}
+
+/// Builds an `InMemoryFetcher` holding the accounts of a set of domains
+#[derive(Default)]
+pub struct LedgerBuilder {
+    accounts: BTreeMap<Pubkey, Account>,
+    // Domain key -> owner stored in the name registry
+    owners: HashMap<Pubkey, Pubkey>,
+    config: SnsConfig,
+}
+
+impl LedgerBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Derives the accounts of the given cluster, which is also the config of the built fetcher
+    pub fn with_config(config: impl Into<SnsConfig>) -> Self {
+        Self {
+            config: config.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Adds an arbitrary account
+    pub fn account(mut self, key: Pubkey, account: Account) -> Self {
+        self.accounts.insert(key, account);
+        self
+    }
+
+    /// Registers a domain or a subdomain with its reverse lookup account
+    ///
+    /// The parent of a subdomain needs to be registered for the reverse lookup to resolve.
+    pub fn domain(self, domain: &str, owner: Pubkey) -> Self {
+        self.domain_with_data(domain, owner, vec![])
+    }
+
+    pub fn domain_with_data(mut self, domain: &str, owner: Pubkey, data: Vec<u8>) -> Self {
+        let leaf = self
+            .config
+            .get_domain_chain(domain)
+            .expect("Invalid domain")
+            .pop()
+            .unwrap();
+        let is_sub = leaf.parent != self.config.root_domain_account;
+        self = self.registry(leaf.key, leaf.parent, owner, Pubkey::default(), data);
+
+        let reverse_key = self
+            .config
+            .derive_reverse(&leaf.key, is_sub.then_some(&leaf.parent));
+        let label = if is_sub {
+            format!("\0{}", leaf.label)
+        } else {
+            leaf.label
+        };
+        let reverse_data = [&(label.len() as u32).to_le_bytes(), label.as_bytes()].concat();
+        let reverse_class = self.config.reverse_lookup_class;
+        self.registry(
+            reverse_key,
+            if is_sub {
+                leaf.parent
+            } else {
+                Pubkey::default()
+            },
+            reverse_class,
+            reverse_class,
+            reverse_data,
+        )
+    }
+
+    /// Adds a record V1 owned by the domain owner, `data` is the raw content of the account
+    pub fn record_v1(self, domain: &str, record: impl Into<RecordKey>, data: &[u8]) -> Self {
+        let domain_key = self.domain_key(domain);
+        let key = self.record_key(domain, record, RecordVersion::V1);
+        let owner = self.owner(&domain_key);
+        self.registry(key, domain_key, owner, Pubkey::default(), data.to_vec())
+    }
+
+    /// Adds a SOL record V1 pointing to `target`, signed by `signer`
+    pub fn sol_record_v1(self, domain: &str, target: &Pubkey, signer: &Keypair) -> Self {
+        let key = self.record_key(domain, Record::Sol, RecordVersion::V1);
+        let data = sign_sol_record(target, &key, signer).expect("Invalid signer");
+        self.record_v1(domain, Record::Sol, &data)
+    }
+
+    /// Adds a record V2 owned by the domain owner
+    pub fn record_v2(
+        self,
+        domain: &str,
+        record: impl Into<RecordKey>,
+        content: MockRecordV2,
+    ) -> Self {
+        let domain_key = self.domain_key(domain);
+        let key = self.record_key(domain, record, RecordVersion::V2);
+        let owner = self.owner(&domain_key);
+        self.registry(
+            key,
+            domain_key,
+            owner,
+            CENTRAL_STATE_RECORD_V2,
+            content.serialize(),
+        )
+    }
+
+    /// Tokenizes a registered domain: the NFT is held by `holder` and the name registry is
+    /// transferred to the tokenizer record, as done by the name tokenizer program
+    pub fn tokenize(mut self, domain: &str, holder: Pubkey) -> Self {
+        let domain_key = self.domain_key(domain);
+        let tokenizer = self.config.name_tokenizer_id;
+        let registry = self
+            .accounts
+            .get_mut(&domain_key)
+            .expect("The domain is not registered");
+        let (nft_record_key, nonce) = NftRecord::find_key(&domain_key, &tokenizer);
+        registry.data[32..64].copy_from_slice(nft_record_key.as_ref());
+
+        let mint_key = self.config.get_domain_mint(&domain_key);
+        let mint = Mint {
+            mint_authority: COption::Some(tokenizer),
+            supply: 1,
+            decimals: 0,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        };
+        let mut data = vec![0; Mint::LEN];
+        Mint::pack(mint, &mut data).unwrap();
+        self = self.account(mint_key, account(spl_token::ID, data));
+
+        let token_account = TokenAccount {
+            mint: mint_key,
+            owner: holder,
+            amount: 1,
+            state: AccountState::Initialized,
+            ..Default::default()
+        };
+        let mut data = vec![0; TokenAccount::LEN];
+        TokenAccount::pack(token_account, &mut data).unwrap();
+        let token_key =
+            spl_associated_token_account::get_associated_token_address(&holder, &mint_key);
+        self = self.account(token_key, account(spl_token::ID, data));
+
+        let nft_record = NftRecord::new(nonce, holder, domain_key, mint_key);
+        self.account(
+            nft_record_key,
+            account(tokenizer, nft_record.try_to_vec().unwrap()),
+        )
+    }
+
+    /// Bridges a registered domain to another chain: the name registry is transferred to the warp
+    /// emitter and the owner is stored in the `XDomain` account, as done by the warp program
+    ///
+    /// Requires `SnsConfig::warp_program_id`.
+    pub fn bridge(mut self, domain: &str, chain: u16, address: [u8; 32]) -> Self {
+        let domain_key = self.domain_key(domain);
+        let warp_program_id = self
+            .config
+            .warp_program_id
+            .expect("The warp program is not set");
+        let registry = self
+            .accounts
+            .get_mut(&domain_key)
+            .expect("The domain is not registered");
+        registry.data[32..64].copy_from_slice(self.config.warp_emitter.as_ref());
+
+        let mut x_domain = XDomain {
+            nonce: 0,
+            owner_chain: chain,
+            owner_address: address,
+            name_account: domain_key,
+        };
+        let key = x_domain.derive_key(&warp_program_id);
+        self.account(
+            key,
+            account(warp_program_id, x_domain.try_to_vec().unwrap()),
+        )
+    }
+
+    /// Sets the favourite domain of `owner`
+    pub fn favourite_domain(self, owner: &Pubkey, domain: &str) -> Self {
+        let domain_key = self.domain_key(domain);
+        // Tag::FavouriteDomain followed by the name account
+        let data = [&[4u8], domain_key.as_ref()].concat();
+        let key = self.config.derive_favourite_domain_key(owner);
+        let program_id = self.config.name_offers_program_id;
+        self.account(key, account(program_id, data))
+    }
+
+    pub fn build(self) -> InMemoryFetcher {
+        let config = self.config;
+        self.accounts
+            .into_iter()
+            .collect::<InMemoryFetcher>()
+            .with_config(config)
+    }
+
+    fn domain_key(&self, domain: &str) -> Pubkey {
+        self.config.get_domain_key(domain).expect("Invalid domain")
+    }
+
+    fn record_key(
+        &self,
+        domain: &str,
+        record: impl Into<RecordKey>,
+        version: RecordVersion,
+    ) -> Pubkey {
+        self.config
+            .get_record_key(domain, record, version)
+            .expect("Invalid domain")
+    }
+
+    fn owner(&self, domain_key: &Pubkey) -> Pubkey {
+        *self
+            .owners
+            .get(domain_key)
+            .expect("The domain is not registered")
+    }
+
+    fn registry(
+        mut self,
+        key: Pubkey,
+        parent: Pubkey,
+        owner: Pubkey,
+        class: Pubkey,
+        data: Vec<u8>,
+    ) -> Self {
+        let header = NameRecordHeader {
+            parent_name: parent,
+            owner,
+            class,
+        };
+        let mut buffer = vec![0; NameRecordHeader::LEN];
+        header.pack_into_slice(&mut buffer);
+        buffer.extend(data);
+        self.owners.insert(key, owner);
+        self.account(key, account(spl_name_service::ID, buffer))
+    }
+}
+
+fn account(owner: Pubkey, data: Vec<u8>) -> Account {
+    Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
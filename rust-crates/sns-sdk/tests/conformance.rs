@@ -0,0 +1,90 @@
+//! Checks the derivation functions against the test vectors shared by all the SNS SDKs
+//! (see `test-vectors/derivation.json` at the root of the repository). The JS SDK checks the same
+//! file in `js/tests/conformance.test.ts`.
+
+use serde::Deserialize;
+use sns_sdk::{
+    config::SnsConfig,
+    domain_name::DomainName,
+    record::{Record, RecordVersion},
+};
+use solana_program::pubkey::Pubkey;
+use std::str::FromStr;
+
+#[derive(Deserialize)]
+struct Vectors {
+    derivation: Vec<DerivationVector>,
+}
+
+#[derive(Deserialize)]
+struct DerivationVector {
+    domain: String,
+    domain_key: String,
+    reverse_key: String,
+    records: Vec<RecordVector>,
+}
+
+#[derive(Deserialize)]
+struct RecordVector {
+    record: String,
+    v1: String,
+    v2: String,
+}
+
+fn load() -> Vectors {
+    let path = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../../test-vectors/derivation.json"
+    );
+    let data = std::fs::read_to_string(path).unwrap();
+    serde_json::from_str(&data).unwrap()
+}
+
+fn key(s: &str) -> Pubkey {
+    Pubkey::from_str(s).unwrap()
+}
+
+#[test]
+fn derivation_vectors() {
+    let vectors = load();
+    assert!(!vectors.derivation.is_empty());
+    for v in vectors.derivation {
+        let domain = v.domain.as_str();
+        assert_eq!(
+            SnsConfig::MAINNET.get_domain_key(domain).unwrap(),
+            key(&v.domain_key),
+            "{domain}"
+        );
+        // Parsing must keep the codepoints hashed on-chain
+        let parsed = DomainName::parse(domain).unwrap();
+        assert_eq!(
+            SnsConfig::MAINNET.get_domain_key(&parsed).unwrap(),
+            key(&v.domain_key),
+            "{domain}"
+        );
+        assert_eq!(
+            SnsConfig::MAINNET.get_reverse_key(domain).unwrap(),
+            key(&v.reverse_key),
+            "{domain}"
+        );
+        for r in v.records {
+            let record = Record::try_from_str(&r.record).unwrap();
+            assert_eq!(
+                SnsConfig::MAINNET
+                    .get_record_key(domain, record, RecordVersion::V1)
+                    .unwrap(),
+                key(&r.v1),
+                "{domain} {}",
+                r.record
+            );
+            assert_eq!(
+                SnsConfig::MAINNET
+                    .get_record_key(domain, record, RecordVersion::V2)
+                    .unwrap(),
+                key(&r.v2),
+                "{domain} {}",
+                r.record
+            );
+        }
+    }
+}